@@ -1,263 +1,200 @@
-#![allow(unused)]
-use std::fs::File;
-use std::io::{Write, BufRead, BufReader};
+use std::io;
+use std::process;
 use std::time::SystemTime;
-use std::path::Path;
-use std::sync::Arc;
-use std::cmp;
-
-use csv::{ReaderBuilder, ByteRecord};
-
-use rayon::prelude::*;
 
+use clap::{Parser, Subcommand, ValueEnum};
+#[cfg(feature = "bench")]
+use trade_core::benchmark::CountingAllocator;
+use trade_core::cli::{InputArgs, OutputArgs, SortBenchArgs, SortKey};
+use trade_core::external::{ExternalSort, ExternalSortError, ExternalSortReport};
+use trade_core::order::OrderSpec;
+use trade_core::sort::{counting_sort_by_key, merge_sort_by, radix_sort_by_key};
+use trade_core::{print_records, prompt, Data};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
+// Lets `bench` report the peak memory of each sort; only in `bench` builds,
+// so the other commands keep the plain system allocator.
+#[cfg(feature = "bench")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[derive(Parser)]
+#[command(about = "Sort trade records with counting sort, radix sort or parallel merge sort")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Runs the interactive menu when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl Data {
-    fn new() -> Data {
-        Data {
-            direction: String::new(),
-            year: 0,
-            date: String::new(),
-            weekday: String::new(),
-            country: String::new(),
-            comodity: String::new(),
-            transport_mode: String::new(),
-            measure: String::new(),
-            value: 0,
-            cumulative: 0,
-        }
-    }
-}
-
-fn compare_data(d1: &Data, d2: &Data) -> cmp::Ordering {
-    let transform_date = |date: &str| -> String {
-        let parts: Vec<&str> = date.split('/').collect();
-        format!("{}/{}/{}", parts[2], parts[1], parts[0])
-    };
-    let transformed_date1 = transform_date(&d1.date);
-    let transformed_date2 = transform_date(&d2.date);
-
-    let date_cmp = transformed_date1.cmp(&transformed_date2);
-    if date_cmp == cmp::Ordering::Equal {
-        d1.value.cmp(&d2.value)
-    } else {
-        date_cmp
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Sort the records and print them or write them to a CSV file
+    Sort {
+        #[arg(short, long, value_enum, default_value_t = Algorithm::Merge)]
+        algorithm: Algorithm,
+
+        #[arg(short, long, value_enum, default_value_t = SortKey::Date)]
+        key: SortKey,
+
+        /// Multi-key order instead of --key, e.g. country,date:desc,value
+        /// (modifiers: asc, desc, nulls-first, nulls-last)
+        #[arg(long, conflicts_with = "key")]
+        order: Option<OrderSpec>,
+
+        /// Sort on disk in runs instead of loading the whole file (merge sort
+        /// only); without --output the sorted CSV goes to stdout
+        #[arg(long)]
+        external: bool,
+
+        /// Records per in-memory run for --external
+        #[arg(long, default_value_t = 100_000, requires = "external")]
+        run_len: usize,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Time counting, merge, heap and quick sort over generated inputs at
+    /// several sizes and thread counts, with comparison counts (and peak
+    /// memory when built with `--features bench`)
+    Bench(SortBenchArgs),
 }
 
-// Modified counting sort function that sorts by value first and then by date.
-fn counting_sort(data: &mut Vec<Data>) {
-    let min_value = data.iter().map(|d| d.value).min().unwrap() as usize;
-    let max_value = data.iter().map(|d| d.value).max().unwrap() as usize;
-
-    let mut count_vec = vec![0; max_value - min_value + 1];
-    for d in data.iter() {
-        let value = d.value as usize - min_value;
-        count_vec[value] += 1;
-    }
-
-    let mut total = 0;
-    for count in count_vec.iter_mut() {
-        let old_count = *count;
-        *count = total;
-        total += old_count;
-    }
-
-    // Sort the data vector by date using the custom comparison function.
-    data.sort_by(compare_data);
-
-    // Reclaim memory for the count_vec.
-    count_vec.shrink_to_fit();
+#[derive(Clone, Copy, ValueEnum)]
+enum Algorithm {
+    Counting,
+    Radix,
+    Merge,
 }
 
-fn merge_sort_par(data: &mut [Data], buffer: &mut [Data]) {
-    let len = data.len();
-    if len <= 1 {
-        return;
+fn sort(data: &mut [Data], algorithm: Algorithm, order: &OrderSpec) -> Result<(), String> {
+    if let Algorithm::Merge = algorithm {
+        merge_sort_by(data, |a, b| order.compare(a, b));
+        return Ok(());
     }
 
-    let mid = len / 2;
-    let (left, right) = data.split_at_mut(mid);
-    let (left_buf, right_buf) = buffer.split_at_mut(mid);
-
-    rayon::join(|| merge_sort_par(left, left_buf), || merge_sort_par(right, right_buf));
-
-    merge(left, right, buffer);
-    
-    // Swapping elements instead of cloning
-    for (d, b) in data.iter_mut().zip(buffer.iter_mut()) {
-        std::mem::swap(d, b);
+    let passes = order.integer_passes().ok_or("counting and radix sort need numeric or date keys")?;
+    // Both sorts are stable, so sorting by the least significant key first
+    // leaves records with equal later keys in the order of the earlier ones.
+    for pass in passes {
+        match algorithm {
+            Algorithm::Counting => counting_sort_by_key(data, pass),
+            _ => radix_sort_by_key(data, pass),
+        }
     }
+    Ok(())
 }
 
-fn merge(left: &mut [Data], right: &mut [Data], buffer: &mut [Data]) {
-    let (mut left_idx, mut right_idx, mut buf_idx) = (0, 0, 0);
-
-    while buf_idx < buffer.len() {
-        let take_from_left = if left_idx < left.len() && right_idx < right.len() {
-            compare_data(&left[left_idx], &right[right_idx]) == cmp::Ordering::Less
-        } else {
-            left_idx < left.len()
-        };
-
-        if take_from_left {
-            std::mem::swap(&mut buffer[buf_idx], &mut left[left_idx]);
-            left_idx += 1;
-        } else {
-            std::mem::swap(&mut buffer[buf_idx], &mut right[right_idx]);
-            right_idx += 1;
+// Streams `--input` through an external merge sort into `--output`, or into
+// stdout as CSV.
+fn external_sort(input: &InputArgs, order: &OrderSpec, run_len: usize, output: &OutputArgs) -> Result<ExternalSortReport, ExternalSortError> {
+    let sorter = ExternalSort::new().run_len(run_len).loader(input.loader());
+    let path = input.path("effects.csv");
+    match &output.output {
+        Some(out) => sorter.sort_file(&path, &out.to_string_lossy(), |a, b| order.compare(a, b)),
+        None => {
+            let records = input.loader().records(&path)?;
+            sorter.sort_records(records, io::stdout().lock(), |a, b| order.compare(a, b))
         }
-        buf_idx += 1;
     }
 }
 
-fn read_data(filename: &str) -> Vec<Data> {
-    let file = File::open(filename).expect("Unable to open file");
-    let mut rdr = ReaderBuilder::new()
-        .buffer_capacity(1 << 16) // Set buffer capacity to 64 KB
-        .has_headers(true) // Set this to false if your CSV doesn't have headers
-        .delimiter(b',') // Change this if your CSV uses a different delimiter
-        .quote(b'"') // Change this if your CSV uses a different quoting character
-        .escape(Some(b'\\')) // Change this if your CSV uses a different escape character
-        .double_quote(true) // Set this to false if your CSV doesn't use double quote escaping
-        .flexible(false) // Set this to true if your CSV has a variable number of fields per record
-        .from_reader(file);
-    let mut record = ByteRecord::new();
-    let mut data = Vec::with_capacity(111_438); // Preallocate memory based on an estimate
-
-    while rdr.read_byte_record(&mut record).unwrap() {
-        let direction = String::from_utf8_lossy(&record[0]).into_owned();
-        let year = String::from_utf8_lossy(&record[1]).parse::<u16>().unwrap();
-        let date = String::from_utf8_lossy(&record[2]).into_owned();
-        let weekday = String::from_utf8_lossy(&record[3]).into_owned();
-        let country = String::from_utf8_lossy(&record[4]).into_owned();
-        let comodity = String::from_utf8_lossy(&record[5]).into_owned();
-        let transport_mode = String::from_utf8_lossy(&record[6]).into_owned();
-        let measure = String::from_utf8_lossy(&record[7]).into_owned();
-        let value = String::from_utf8_lossy(&record[8]).parse::<u64>().unwrap();
-        let cumulative = String::from_utf8_lossy(&record[9]).parse::<u64>().unwrap();
+fn main() {
+    let cli = Cli::parse();
 
-        data.push(
-            Data {
-                direction,
-                year,
-                date,
-                weekday,
-                country,
-                comodity,
-                transport_mode,
-                measure,
-                value,
-                cumulative,
-            });
+    if let Some(Command::Sort { algorithm, key, order, external: true, run_len, output }) = &cli.command {
+        if !matches!(algorithm, Algorithm::Merge) {
+            eprintln!("--external only supports merge sort");
+            process::exit(1);
+        }
+        let order = order.clone().unwrap_or_else(|| key.order());
+        match external_sort(&cli.input, &order, *run_len, output) {
+            Ok(report) => {
+                for e in report.rejected.iter().chain(&report.coerced) {
+                    eprintln!("{}", e);
+                }
+                match report.runs {
+                    0 => eprintln!("Sorted {} records in memory", report.records),
+                    runs => eprintln!("Sorted {} records in {} runs", report.records, runs),
+                }
+            }
+            Err(e) => {
+                eprintln!("Error sorting file: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
     }
 
-    data
-}
-
-#[allow(dead_code)]
-fn print_data(data: &Vec<Data>) {
-    for d in data {
-        println!("{} {} {} {} {} {} {} {} {} {}",
-            d.direction,
-            d.year,
-            d.date,
-            d.weekday,
-            d.country,
-            d.comodity,
-            d.transport_mode,
-            d.measure,
-            d.value,
-            d.cumulative
-        );
+    if let Some(Command::Bench(bench)) = &cli.command {
+        let data = match bench.load(&cli.input, "effects.csv") {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Error reading file: {}", e);
+                process::exit(1);
+            }
+        };
+        if let Err(e) = bench.run(&data) {
+            eprintln!("Error running benchmark: {}", e);
+            process::exit(1);
+        }
+        return;
     }
 
-    println!("--------------------------------");
-    println!("{} records", data.len());
-    println!("--------------------------------");
-}
-
-#[allow(dead_code)]
-fn save_to_file(data: &Vec<Data>, filename: &str) {
-    let mut file = File::create(filename).expect("Unable to create file");
-    writeln!(file, "Direction,Year,Date,Weekday,Country,Commodity,Transport_Mode,Measure,Value,Cumulative").expect("Unable to write header");
-    let surround_with_quotes_if_comma = |string: &str| -> String {
-        if string.contains(",") {
-            format!("\"{}\"", string)
-        } else {
-            String::from(string)
+    let start = SystemTime::now();
+    let mut data_vector = match cli.input.load("effects.csv") {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
         }
     };
-    for d in data {
-        writeln!(file, "{},{},{},{},{},{},{},{},{},{}",
-            surround_with_quotes_if_comma(d.direction.as_str()),
-            d.year,
-            surround_with_quotes_if_comma(d.date.as_str()),
-            surround_with_quotes_if_comma(d.weekday.as_str()),
-            surround_with_quotes_if_comma(d.country.as_str()),
-            surround_with_quotes_if_comma(d.comodity.as_str()),
-            surround_with_quotes_if_comma(d.transport_mode.as_str()),
-            surround_with_quotes_if_comma(d.measure.as_str()),
-            d.value,
-            d.cumulative
-        ).expect("Unable to write data");
-    }
-}
+    let end = SystemTime::now();
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
-}
+    if let Some(Command::Sort { algorithm, key, order, output, .. }) = cli.command {
+        let order = order.unwrap_or_else(|| key.order());
+        if let Err(e) = sort(&mut data_vector, algorithm, &order) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        if let Err(e) = output.write(&data_vector) {
+            eprintln!("Error writing output: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
-fn main() {
-    let start = SystemTime::now();
-    let mut data_vector = read_data("effects.csv");
-    let end = SystemTime::now();
     println!("Reading data took {} ms", end.duration_since(start).unwrap().as_millis());
-    
+
     println!("Select sorting algorithm:");
     println!("1. Counting sort");
     println!("2. Merge sort");
-    print!("Enter your choice: ");
-    std::io::stdout().flush().unwrap();
-
-    let choice = user_input();
-
-    match choice.as_str() {
-        "1" => {
-            let start = SystemTime::now();
-            counting_sort(&mut data_vector);
-            let end = SystemTime::now();
-            print_data(&data_vector);
-            println!("Counting sort took {} ms", end.duration_since(start).unwrap().as_millis());
+    println!("3. Radix sort");
+    let choice = prompt("Enter your choice: ");
 
-        },
-        "2" => {
-            let start = SystemTime::now();
-            let mut buffer = vec![Data::new(); data_vector.len()];
-            merge_sort_par(&mut data_vector, &mut buffer);
-            let end = SystemTime::now();
-            print_data(&data_vector);
-            println!("Merge sort took {} ms", end.duration_since(start).unwrap().as_millis());
-        },
+    let algorithm = match choice.as_str() {
+        "1" => Algorithm::Counting,
+        "2" => Algorithm::Merge,
+        "3" => Algorithm::Radix,
         _ => {
             println!("Invalid choice");
+            return;
         }
-    }
+    };
+
+    let start = SystemTime::now();
+    sort(&mut data_vector, algorithm, &SortKey::Date.order()).unwrap();
+    let end = SystemTime::now();
+    print_records(&data_vector);
+    let name = match algorithm {
+        Algorithm::Counting => "Counting sort",
+        Algorithm::Radix => "Radix sort",
+        Algorithm::Merge => "Merge sort",
+    };
+    println!("{} took {} ms", name, end.duration_since(start).unwrap().as_millis());
 }
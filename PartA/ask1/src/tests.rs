@@ -1,4 +1,5 @@
 use super::*;
+use trade_core::read_data;
 
 #[cfg(test)]
 mod tests {
@@ -45,7 +46,7 @@ mod tests {
             },
         ];
 
-        counting_sort(&mut data);
+        sort(&mut data, Algorithm::Counting, &SortKey::Date.order()).unwrap();
 
         assert_eq!(data[0].date, "10/01/2023");
         assert_eq!(data[1].date, "02/02/2023");
@@ -93,7 +94,7 @@ mod tests {
             },
         ];
 
-        counting_sort(&mut data);
+        sort(&mut data, Algorithm::Counting, &SortKey::Value.order()).unwrap();
 
         assert_eq!(data[0].value, 1);
         assert_eq!(data[1].value, 2);
@@ -141,8 +142,7 @@ mod tests {
             },
         ];
 
-        let mut buffer = vec![Data::new(); data.len()];
-        merge_sort_par(&mut data, &mut buffer);
+        sort(&mut data, Algorithm::Merge, &SortKey::Date.order()).unwrap();
 
         assert_eq!(data[0].date, "10/01/2023");
         assert_eq!(data[1].date, "02/02/2023");
@@ -190,17 +190,162 @@ mod tests {
             },
         ];
 
-        let mut buffer = vec![Data::new(); data.len()];
-        merge_sort_par(&mut data, &mut buffer);
+        sort(&mut data, Algorithm::Merge, &SortKey::Date.order()).unwrap();
 
         assert_eq!(data[0].value, 1);
         assert_eq!(data[1].value, 2);
         assert_eq!(data[2].value, 3); 
     }
 
+    #[test]
+    fn test_radix_and_counting_match_merge_sort() {
+        let mut expected = read_data("test.csv").unwrap();
+        expected.reverse();
+        let mut counting = expected.clone();
+        let mut radix = expected.clone();
+        sort(&mut expected, Algorithm::Merge, &SortKey::Date.order()).unwrap();
+
+        // Date order with ties broken by value, as the merge sort compares.
+        expected[0].date = expected[1].date.clone();
+        counting[17].date = expected[1].date.clone();
+        radix[17].date = expected[1].date.clone();
+        sort(&mut expected, Algorithm::Merge, &SortKey::Date.order()).unwrap();
+        sort(&mut counting, Algorithm::Counting, &SortKey::Date.order()).unwrap();
+        sort(&mut radix, Algorithm::Radix, &SortKey::Date.order()).unwrap();
+        assert_eq!(counting, expected);
+        assert_eq!(radix, expected);
+
+        sort(&mut radix, Algorithm::Radix, &SortKey::Cumulative.order()).unwrap();
+        assert!(radix.windows(2).all(|w| w[0].cumulative <= w[1].cumulative));
+    }
+
+    #[test]
+    fn test_sort_by_text_key() {
+        let mut data = read_data("test.csv").unwrap();
+        assert!(sort(&mut data, Algorithm::Counting, &SortKey::Country.order()).is_err());
+
+        sort(&mut data, Algorithm::Merge, &SortKey::Commodity.order()).unwrap();
+        assert!(data.windows(2).all(|w| w[0].comodity <= w[1].comodity));
+    }
+
     #[test]
     fn test_read_data_from_csv(){
-        let data = read_data("test.csv");
+        let data = read_data("test.csv").unwrap();
         assert_eq!(data.len(), 18);
     }
+
+    #[test]
+    fn test_cli_sort_arguments() {
+        let cli = Cli::try_parse_from([
+            "parta-ask1", "sort", "--algorithm", "counting", "--key", "value", "--input", "test.csv",
+        ])
+        .unwrap();
+        let data = cli.input.load("effects.csv").unwrap();
+        assert_eq!(data.len(), 18);
+
+        match cli.command {
+            Some(Command::Sort { algorithm, key, external, output, .. }) => {
+                assert!(matches!(algorithm, Algorithm::Counting));
+                assert_eq!(key, SortKey::Value);
+                assert!(!external);
+                assert!(output.output.is_none());
+            }
+            _ => panic!("expected the sort subcommand"),
+        }
+
+        assert!(Cli::try_parse_from(["parta-ask1", "sort", "--key", "weekday"]).is_err());
+        assert!(Cli::try_parse_from(["parta-ask1", "sort", "--run-len", "10"]).is_err());
+        assert!(Cli::try_parse_from(["parta-ask1", "sort", "--key", "value", "--order", "value"]).is_err());
+        assert!(Cli::try_parse_from(["parta-ask1", "sort", "--order", "value:up"]).is_err());
+    }
+
+    #[test]
+    fn test_multi_key_order() {
+        let cli = Cli::try_parse_from(["parta-ask1", "sort", "-a", "radix", "--order", "date:desc,value:desc"]).unwrap();
+        let Some(Command::Sort { algorithm, order: Some(order), .. }) = cli.command else {
+            panic!("expected the sort subcommand with an order");
+        };
+
+        let mut data = read_data("test.csv").unwrap();
+        let mut expected = data.clone();
+        sort(&mut data, algorithm, &order).unwrap();
+        expected.sort_by(|a, b| {
+            let (da, db) = (a.trade_date().unwrap(), b.trade_date().unwrap());
+            db.cmp(&da).then(b.value.cmp(&a.value))
+        });
+        assert_eq!(data, expected);
+
+        let mut counting = read_data("test.csv").unwrap();
+        sort(&mut counting, Algorithm::Counting, &order).unwrap();
+        assert_eq!(counting, expected);
+        assert!(sort(&mut counting, Algorithm::Radix, &"value,country".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_external_sort_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sorted.csv");
+        let cli = Cli::try_parse_from([
+            "parta-ask1", "--input", "test.csv", "sort", "--external", "--run-len", "4", "--key", "value",
+            "--output", path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let Some(Command::Sort { key, run_len, output, .. }) = cli.command else {
+            panic!("expected the sort subcommand");
+        };
+
+        let report = external_sort(&cli.input, &key.order(), run_len, &output).unwrap();
+        assert_eq!((report.records, report.runs), (18, 5));
+
+        let mut expected = read_data("test.csv").unwrap();
+        sort(&mut expected, Algorithm::Merge, &SortKey::Value.order()).unwrap();
+        assert_eq!(read_data(path.to_str().unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_bench_command() {
+        use trade_core::benchmark::{SortAlgorithm, SortInput};
+
+        let cli = Cli::try_parse_from([
+            "parta-ask1", "bench", "-a", "merge,quick", "--inputs", "reversed,real", "-s", "100,500", "-t", "1,2", "-f", "json",
+        ])
+        .unwrap();
+        let Some(Command::Bench(bench)) = cli.command else {
+            panic!("expected the bench subcommand");
+        };
+        assert_eq!(bench.algorithm, [SortAlgorithm::Merge, SortAlgorithm::Quick]);
+        assert_eq!(bench.inputs, [SortInput::Reversed, SortInput::Real]);
+        assert_eq!((bench.sizes.as_slice(), bench.threads.as_slice(), bench.repeats), ([100, 500].as_slice(), [1, 2].as_slice(), 3));
+
+        let output = tempfile::NamedTempFile::new().unwrap();
+        let bench = SortBenchArgs { output: Some(output.path().to_path_buf()), ..bench };
+        bench.run(&read_data("test.csv").unwrap()).unwrap();
+        let report = std::fs::read_to_string(output.path()).unwrap();
+        // Two inputs, two sizes, two thread counts, two sorts.
+        assert_eq!(report.matches("\"algorithm\"").count(), 16);
+        // Memory is only measured when the counting allocator is installed.
+        assert_eq!(report.contains("null"), !cfg!(feature = "bench"));
+        assert!(Cli::try_parse_from(["parta-ask1", "bench", "-a", "bogo"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_loads_the_export_only_for_real_input() {
+        let cli = Cli::try_parse_from(["parta-ask1", "bench", "--inputs", "random,sorted", "-i", "does_not_exist.csv"]).unwrap();
+        let Some(Command::Bench(bench)) = &cli.command else {
+            panic!("expected the bench subcommand");
+        };
+        assert!(bench.load(&cli.input, "effects.csv").unwrap().is_empty());
+
+        let cli = Cli::try_parse_from(["parta-ask1", "bench", "-i", "does_not_exist.csv"]).unwrap();
+        let Some(Command::Bench(bench)) = &cli.command else {
+            panic!("expected the bench subcommand");
+        };
+        assert!(bench.load(&cli.input, "effects.csv").is_err());
+
+        let cli = Cli::try_parse_from(["parta-ask1", "bench", "--inputs", "real", "-i", "test.csv"]).unwrap();
+        let Some(Command::Bench(bench)) = &cli.command else {
+            panic!("expected the bench subcommand");
+        };
+        assert_eq!(bench.load(&cli.input, "effects.csv").unwrap().len(), read_data("test.csv").unwrap().len());
+    }
 }
@@ -1,214 +1,202 @@
-#![allow(unused)]
-use std::fs::File;
-use std::io::Write;
+use std::cmp::Ordering;
+use std::process;
 use std::time::SystemTime;
 
-use csv::{ReaderBuilder, ByteRecord};
-
+use clap::{Parser, Subcommand, ValueEnum};
+#[cfg(feature = "bench")]
+use trade_core::benchmark::CountingAllocator;
+use trade_core::cli::{InputArgs, OutputArgs, SortBenchArgs, SortKey};
+use trade_core::order::OrderSpec;
+use trade_core::select::{bottom_k, percentile, top_k};
+use trade_core::sort::{heap_sort_with_arity, par_heap_sort_by, quick_sort_by, stable_quick_sort_by, stable_sort_with};
+use trade_core::{print_records, prompt, Data};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-use rayon::prelude::*;
-
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
+// Lets `bench` report the peak memory of each sort; only in `bench` builds,
+// so the other commands keep the plain system allocator.
+#[cfg(feature = "bench")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[derive(Parser)]
+#[command(about = "Sort trade records with heap sort or parallel quick sort, or pick the top records")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Runs the interactive menu when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl Data {
-    fn new() -> Data {
-        Data {
-            direction: String::new(),
-            year: 0,
-            date: String::new(),
-            weekday: String::new(),
-            country: String::new(),
-            comodity: String::new(),
-            transport_mode: String::new(),
-            measure: String::new(),
-            value: 0,
-            cumulative: 0,
-        }
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Sort the records and print them or write them to a CSV file
+    Sort {
+        #[arg(short, long, value_enum, default_value_t = Algorithm::Quick)]
+        algorithm: Algorithm,
+
+        #[arg(short, long, value_enum, default_value_t = SortKey::Cumulative)]
+        key: SortKey,
+
+        /// Multi-key order instead of --key, e.g. country,date:desc,value
+        /// (modifiers: asc, desc, nulls-first, nulls-last)
+        #[arg(long, conflicts_with = "key")]
+        order: Option<OrderSpec>,
+
+        /// Keep records that compare equal in their input order
+        #[arg(long)]
+        stable: bool,
+
+        /// Children per node for the heap sorts
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(2..))]
+        arity: u8,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print or save the records with the largest key, largest first, without
+    /// sorting the rest
+    Top {
+        #[arg(short, long, value_enum, default_value_t = SelectKey::Cumulative)]
+        key: SelectKey,
+
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print or save the records with the smallest key, smallest first
+    Bottom {
+        #[arg(short, long, value_enum, default_value_t = SelectKey::Cumulative)]
+        key: SelectKey,
+
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print or save the record at a percentile of the key (50 is the median)
+    Percentile {
+        #[arg(short, long, value_enum, default_value_t = SelectKey::Cumulative)]
+        key: SelectKey,
+
+        #[arg(short, long, default_value_t = 50.0)]
+        percent: f64,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Time counting, merge, heap and quick sort over generated inputs at
+    /// several sizes and thread counts, with comparison counts (and peak
+    /// memory when built with `--features bench`)
+    Bench(SortBenchArgs),
 }
-fn heapify(data: &mut [Data], n: usize, i: usize) {
-    let mut largest = i;
-    let l = 2 * i + 1;
-    let r = 2 * i + 2;
-
-    if l < n && data[l].cumulative > data[largest].cumulative {
-        largest = l;
-    }
-
-    if r < n && data[r].cumulative > data[largest].cumulative {
-        largest = r;
-    }
 
-    if largest != i {
-        data.swap(i, largest);
-        heapify(data, n, largest);
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+enum SelectKey {
+    Value,
+    Cumulative,
 }
 
-fn heap_sort(data: &mut [Data]) -> &mut [Data] {
-    let n = data.len();
-    for i in (0..n / 2).rev() {
-        heapify(data, n, i);
-    }
-
-    for i in (0..n).rev() {
-        data.swap(0, i);
-        heapify(&mut data[..i], i, 0);
-    }
-
-    data
-}
-
-fn quick_sort_par(data: &mut [Data]) {
-    if data.len() <= 1 {
-        return;
-    }
-
-    let pivot_index = partition(data);
-
-    let (left, right) = data.split_at_mut(pivot_index);
-
-    rayon::join(|| quick_sort_par(left), || quick_sort_par(&mut right[1..]));
-}
-
-fn partition(data: &mut [Data]) -> usize {
-    let pivot_index = data.len() / 2;
-    data.swap(pivot_index, data.len() - 1);
-
-    let mut i = 0;
-    for j in 0..data.len() - 1 {
-        if data[j].cumulative <= data[data.len() - 1].cumulative {
-            data.swap(i, j);
-            i += 1;
+impl SelectKey {
+    fn key(self) -> fn(&Data) -> u64 {
+        match self {
+            SelectKey::Value => |d| d.value,
+            SelectKey::Cumulative => |d| d.cumulative,
         }
     }
-
-    data.swap(i, data.len() - 1);
-    i
-}
-
-fn read_data(filename: &str) -> Vec<Data> {
-    let file = File::open(filename).expect("Unable to open file");
-    let mut rdr = ReaderBuilder::new()
-        .buffer_capacity(1 << 16) // Set buffer capacity to 64 KB
-        .has_headers(true) // Set this to false if your CSV doesn't have headers
-        .delimiter(b',') // Change this if your CSV uses a different delimiter
-        .quote(b'"') // Change this if your CSV uses a different quoting character
-        .escape(Some(b'\\')) // Change this if your CSV uses a different escape character
-        .double_quote(true) // Set this to false if your CSV doesn't use double quote escaping
-        .flexible(false) // Set this to true if your CSV has a variable number of fields per record
-        .from_reader(file);
-    let mut record = ByteRecord::new();
-    let mut data = Vec::with_capacity(111_438); // Preallocate memory based on an estimate
-
-    while rdr.read_byte_record(&mut record).unwrap() {
-        let direction = String::from_utf8_lossy(&record[0]).into_owned();
-        let year = String::from_utf8_lossy(&record[1]).parse::<u16>().unwrap();
-        let date = String::from_utf8_lossy(&record[2]).into_owned();
-        let weekday = String::from_utf8_lossy(&record[3]).into_owned();
-        let country = String::from_utf8_lossy(&record[4]).into_owned();
-        let comodity = String::from_utf8_lossy(&record[5]).into_owned();
-        let transport_mode = String::from_utf8_lossy(&record[6]).into_owned();
-        let measure = String::from_utf8_lossy(&record[7]).into_owned();
-        let value = String::from_utf8_lossy(&record[8]).parse::<u64>().unwrap();
-        let cumulative = String::from_utf8_lossy(&record[9]).parse::<u64>().unwrap();
-
-        data.push(
-            Data {
-                direction,
-                year,
-                date,
-                weekday,
-                country,
-                comodity,
-                transport_mode,
-                measure,
-                value,
-                cumulative,
-            });
-    }
-
-    data
 }
 
-fn print_data(data: &Vec<Data>) {
-    for d in data {
-        println!("{} {} {} {} {} {} {} {} {} {}",
-            d.direction,
-            d.year,
-            d.date,
-            d.weekday,
-            d.country,
-            d.comodity,
-            d.transport_mode,
-            d.measure,
-            d.value,
-            d.cumulative
-        );
-    }
-
-    println!("--------------------------------");
-    println!("{} records", data.len());
-    println!("--------------------------------");
+#[derive(Clone, Copy, ValueEnum)]
+enum Algorithm {
+    Heap,
+    ParallelHeap,
+    Quick,
 }
 
-#[allow(dead_code)]
-fn save_to_file(data: &Vec<Data>, filename: &str) {
-    let mut file = File::create(filename).expect("Unable to create file");
-    writeln!(file, "Direction,Year,Date,Weekday,Country,Commodity,Transport_Mode,Measure,Value,Cumulative").expect("Unable to write header");
-    for d in data {
-        writeln!(file, "{},{},{},{},{},{},{},{},{},{}",
-            surround_with_quotes_if_comma(d.direction.as_str()),
-            d.year,
-            surround_with_quotes_if_comma(d.date.as_str()),
-            surround_with_quotes_if_comma(d.weekday.as_str()),
-            surround_with_quotes_if_comma(d.country.as_str()),
-            surround_with_quotes_if_comma(d.comodity.as_str()),
-            surround_with_quotes_if_comma(d.transport_mode.as_str()),
-            surround_with_quotes_if_comma(d.measure.as_str()),
-            d.value,
-            d.cumulative
-        ).expect("Unable to write data");
+fn sort(data: &mut [Data], algorithm: Algorithm, order: &OrderSpec, arity: usize) {
+    let compare = |a: &Data, b: &Data| order.compare(a, b);
+    match (algorithm, order.is_stable()) {
+        (_, false) => sort_by(data, algorithm, arity, compare),
+        (Algorithm::Quick, true) => stable_quick_sort_by(data, compare),
+        // The heap sorts are not stable either; sort the positions instead.
+        (_, true) => stable_sort_with(data, compare, |positions, compare| sort_by(positions, algorithm, arity, compare)),
     }
 }
 
-fn surround_with_quotes_if_comma(string: &str) -> String {
-    if string.contains(",") {
-        format!("\"{}\"", string)
-    } else {
-        String::from(string)
+fn sort_by<T, F>(data: &mut [T], algorithm: Algorithm, arity: usize, compare: F)
+where
+    T: Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    match algorithm {
+        Algorithm::Heap => heap_sort_with_arity(data, arity, compare),
+        Algorithm::ParallelHeap => par_heap_sort_by(data, compare),
+        Algorithm::Quick => quick_sort_by(data, compare),
     }
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
-}
-
 fn main() {
-    // let mut input = String::new();
+    let cli = Cli::parse();
+
+    if let Some(Command::Bench(bench)) = &cli.command {
+        let data = match bench.load(&cli.input, "effects.csv") {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Error reading file: {}", e);
+                process::exit(1);
+            }
+        };
+        if let Err(e) = bench.run(&data) {
+            eprintln!("Error running benchmark: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
     let start = SystemTime::now();
-    let mut data = read_data("effects.csv");
+    let mut data = match cli.input.load("effects.csv") {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
+        }
+    };
     let end = SystemTime::now();
+
+    if let Some(command) = cli.command {
+        let (records, output) = match command {
+            Command::Sort { algorithm, key, order, stable, arity, output } => {
+                let order = order.unwrap_or_else(|| key.order()).stable(stable);
+                sort(&mut data, algorithm, &order, arity as usize);
+                (data, output)
+            }
+            Command::Top { key, limit, output } => (top_k(&data, limit, key.key()), output),
+            Command::Bottom { key, limit, output } => (bottom_k(&data, limit, key.key()), output),
+            Command::Percentile { key, percent, output } => {
+                if !(0.0..=100.0).contains(&percent) {
+                    eprintln!("Percentile must be between 0 and 100");
+                    process::exit(1);
+                }
+                (percentile(&data, percent, key.key()).into_iter().collect(), output)
+            }
+            Command::Bench(_) => unreachable!("bench returns before the records are loaded"),
+        };
+        if let Err(e) = output.write(&records) {
+            eprintln!("Error writing output: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     println!("Reading data took {} ms", end.duration_since(start).unwrap().as_millis());
-    let len = data.len();
-    
+
     println!("--------------------------------");
     println!("{} records", data.len());
     println!("--------------------------------");
@@ -216,29 +204,28 @@ fn main() {
     println!("Sort with:");
     println!("1. Heap Sort");
     println!("2. Quick Sort");
+    println!("3. Parallel Heap Sort");
 
-    print!("Enter your choice: ");
-    std::io::stdout().flush().unwrap();
-    let choice = user_input();
-
-    match choice.as_str() {
-        "1" => {
-            let start = SystemTime::now();
-            heap_sort(&mut data);
-            let end = SystemTime::now();
-            print_data(&data);
-            println!("Heap Sort took {} ms", end.duration_since(start).unwrap().as_millis());
-        },
-
-        "2" => {
-            let start = SystemTime::now();
-            quick_sort_par(&mut data);
-            let end = SystemTime::now();
-            print_data(&data);
-            println!("Quick Sort took {} ms", end.duration_since(start).unwrap().as_millis());
-        },
-
-        _ => println!("Invalid choice"),
-    }
+    let choice = prompt("Enter your choice: ");
+
+    let algorithm = match choice.as_str() {
+        "1" => Algorithm::Heap,
+        "2" => Algorithm::Quick,
+        "3" => Algorithm::ParallelHeap,
+        _ => {
+            println!("Invalid choice");
+            return;
+        }
+    };
 
-}
\ No newline at end of file
+    let start = SystemTime::now();
+    sort(&mut data, algorithm, &SortKey::Cumulative.order(), 2);
+    let end = SystemTime::now();
+    print_records(&data);
+    let name = match algorithm {
+        Algorithm::Heap => "Heap Sort",
+        Algorithm::ParallelHeap => "Parallel Heap Sort",
+        Algorithm::Quick => "Quick Sort",
+    };
+    println!("{} took {} ms", name, end.duration_since(start).unwrap().as_millis());
+}
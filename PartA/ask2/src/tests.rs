@@ -1,6 +1,7 @@
 use crate::Data;
-use crate::heap_sort;
-use crate::quick_sort_par;
+use crate::{sort, Algorithm, Cli, Command, SelectKey};
+use trade_core::cli::SortKey;
+use trade_core::order::OrderSpec;
 
 #[cfg(test)]
 mod ask2 {
@@ -9,12 +10,12 @@ mod ask2 {
 
     #[test]
     fn test_heap_sort() {
-        let nums = vec![1, 4, 2, 0, 3];
+        let nums = [1, 4, 2, 0, 3];
         let mut data = vec![Data::new(); nums.len()];
         for (i, d) in data.iter_mut().enumerate() {
             d.cumulative = nums[i] as u64;
         }
-        heap_sort(&mut data);
+        sort(&mut data, Algorithm::Heap, &SortKey::Cumulative.order(), 2);
         for (i, d) in data.iter().enumerate() {
             assert_eq!(d.cumulative, i as u64);
         }
@@ -22,16 +23,70 @@ mod ask2 {
 
     #[test]
     fn test_quick_sort_par() {
-        let nums = vec![1, 4, 2, 0, 3];
+        let nums = [1, 4, 2, 0, 3];
         let mut data = vec![Data::new(); nums.len()];
         for (i, d) in data.iter_mut().enumerate() {
             d.cumulative = nums[i] as u64;
         }
-        let len = data.len();
-        quick_sort_par(&mut data);
+        sort(&mut data, Algorithm::Quick, &SortKey::Cumulative.order(), 2);
         for (i, d) in data.iter().enumerate() {
             assert_eq!(d.cumulative, i as u64);
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_cli_sort_arguments() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["parta-ask2", "sort", "-a", "heap", "-o", "sorted.csv"]).unwrap();
+        match cli.command {
+            Some(Command::Sort { algorithm, key, order, stable, arity, output }) => {
+                assert!(order.is_none() && !stable);
+                assert_eq!(arity, 2);
+                assert!(matches!(algorithm, Algorithm::Heap));
+                assert_eq!(key, SortKey::Cumulative);
+                assert_eq!(output.output.unwrap().to_str(), Some("sorted.csv"));
+            }
+            _ => panic!("expected the sort subcommand"),
+        }
+
+        assert!(Cli::try_parse_from(["parta-ask2", "sort", "--arity", "1"]).is_err());
+        let cli = Cli::try_parse_from(["parta-ask2", "top", "--key", "value", "-l", "3"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Top { key: SelectKey::Value, limit: 3, .. })));
+        assert!(Cli::try_parse_from(["parta-ask2", "bottom", "--key", "country"]).is_err());
+        let cli = Cli::try_parse_from(["parta-ask2", "percentile", "-p", "90"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Percentile { key: SelectKey::Cumulative, .. })));
+    }
+
+    #[test]
+    fn test_heap_variants() {
+        let nums = [5, 1, 4, 2, 0, 3, 9, 7, 8, 6];
+        for (algorithm, arity) in [(Algorithm::Heap, 3), (Algorithm::Heap, 4), (Algorithm::ParallelHeap, 2)] {
+            let mut data = vec![Data::new(); nums.len()];
+            for (i, d) in data.iter_mut().enumerate() {
+                d.value = nums[i] as u64;
+            }
+            sort(&mut data, algorithm, &SortKey::Value.order(), arity);
+            for (i, d) in data.iter().enumerate() {
+                assert_eq!(d.value, i as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_flag_keeps_input_order() {
+        let mut data = vec![Data::new(); 40];
+        for (i, d) in data.iter_mut().enumerate() {
+            d.value = (i % 4) as u64;
+            d.cumulative = i as u64;
+        }
+        let order = "value:desc".parse::<OrderSpec>().unwrap().stable(true);
+        for algorithm in [Algorithm::Heap, Algorithm::ParallelHeap, Algorithm::Quick] {
+            let mut sorted = data.clone();
+            sort(&mut sorted, algorithm, &order, 3);
+            let mut expected = data.clone();
+            expected.sort_by_key(|d| std::cmp::Reverse(d.value));
+            assert_eq!(sorted, expected);
+        }
+    }
+}
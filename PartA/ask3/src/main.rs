@@ -1,179 +1,211 @@
-#[allow(unused)]
 use std::fs::File;
 use std::io::{self, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
 use std::time::SystemTime;
 
-use csv::{ByteRecord, ReaderBuilder};
+use clap::{Parser, Subcommand};
+use trade_core::benchmark::{write_search_report, ReportFormat, SearchBenchmark, Workload};
+use trade_core::cli::{InputArgs, SearchAlgorithm};
+use trade_core::search::{date_bounds, equal_range_around, equal_range_by_key, search_date, SearchStats};
+use trade_core::{prompt, Data, TradeDate};
 
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
+// The loader rejects rows whose date does not parse, so every record has one.
+fn date_key(data: &Data) -> TradeDate {
+    data.trade_date().expect("the loader checks every date")
 }
 
-fn convert_date_to_days(date_str: &str) -> u32 {
-    let mut parts = date_str.split('/');
-    let day = parts.next().unwrap().parse::<u32>().unwrap();
-    let month = parts.next().unwrap().parse::<u32>().unwrap();
-    let year = parts.next().unwrap().parse::<u32>().unwrap();
-    
-    year * 365 + month * 30 + day
-}
+fn in_range(data: &[Data], key: TradeDate) -> bool {
+    let max_date = date_key(&data[data.len() - 1]);
+    let min_date = date_key(&data[0]);
 
-fn read_data(filename: &str) -> Vec<Data> {
-    let file = File::open(filename).expect("Unable to open file");
-    let mut rdr = ReaderBuilder::new()
-        .buffer_capacity(1 << 16) // Set buffer capacity to 64 KB
-        .has_headers(true) // Set this to false if your CSV doesn't have headers
-        .delimiter(b',') // Change this if your CSV uses a different delimiter
-        .quote(b'"') // Change this if your CSV uses a different quoting character
-        .escape(Some(b'\\')) // Change this if your CSV uses a different escape character
-        .double_quote(true) // Set this to false if your CSV doesn't use double quote escaping
-        .flexible(false) // Set this to true if your CSV has a variable number of fields per record
-        .from_reader(file);
-    let mut record = ByteRecord::new();
-    let mut data = Vec::with_capacity(111_438); // Preallocate memory based on an estimate
-
-    while rdr.read_byte_record(&mut record).unwrap() {
-        let direction = String::from_utf8_lossy(&record[0]).into_owned();
-        let year = String::from_utf8_lossy(&record[1]).parse::<u16>().unwrap();
-        let date = String::from_utf8_lossy(&record[2]).into_owned();
-        let weekday = String::from_utf8_lossy(&record[3]).into_owned();
-        let country = String::from_utf8_lossy(&record[4]).into_owned();
-        let comodity = String::from_utf8_lossy(&record[5]).into_owned();
-        let transport_mode = String::from_utf8_lossy(&record[6]).into_owned();
-        let measure = String::from_utf8_lossy(&record[7]).into_owned();
-        let value = String::from_utf8_lossy(&record[8]).parse::<u64>().unwrap();
-        let cumulative = String::from_utf8_lossy(&record[9]).parse::<u64>().unwrap();
-
-        data.push(
-            Data {
-                direction,
-                year,
-                date,
-                weekday,
-                country,
-                comodity,
-                transport_mode,
-                measure,
-                value,
-                cumulative,
-            });
-    }
+    (min_date..=max_date).contains(&key)
+}
 
-    data
+fn print_data_line(data: &[Data], index: usize) {
+    println!("Index-> {}: {}", index, data[index]);
 }
 
-fn binary_search(data: &Vec<Data>, low: usize, high: usize, date_key: u32) -> usize {
-    if high < low {
-        return low;
+fn print_matches(data: &[Data], matches: Range<usize>) {
+    if matches.is_empty() {
+        println!("Date not found");
     }
-
-    let mid = (low + high) / 2;
-    let mid_days = convert_date_to_days(&data[mid].date);
-
-    if mid_days == date_key {
-        return mid;
-    } else if mid_days > date_key {
-        return binary_search(data, low, mid - 1, date_key);
-    } else {
-        return binary_search(data, mid + 1, high, date_key);
+    for index in matches {
+        print_data_line(data, index);
     }
 }
 
-fn interpolation_search(data: &Vec<Data>, low: usize, high: usize, date_key: u32) -> usize {
-    if high < low {
-        return low;
-    }
-
-    let mid = low + ((date_key - convert_date_to_days(&data[low].date)) * (high as u32 - low as u32) / (convert_date_to_days(&data[high].date) - convert_date_to_days(&data[low].date))) as usize;
-    let mid_days = convert_date_to_days(&data[mid].date);
-
-    if mid_days == date_key {
-        return mid;
-    } else if mid_days > date_key {
-        return interpolation_search(data, low, mid - 1, date_key);
-    } else {
-        return interpolation_search(data, mid + 1, high, date_key);
+// Every record on `key`: the algorithm finds one of them and the bounds
+// searches widen it to the whole run.
+fn search_all(data: &[Data], key: TradeDate, algorithm: SearchAlgorithm) -> Range<usize> {
+    match search(data, key, algorithm) {
+        Some(index) => equal_range_around(data, index, date_key),
+        None => 0..0,
     }
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    print!("Enter date (dd/mm/yyyy): ");
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
+#[derive(Parser)]
+#[command(about = "Look up trade records by date in a date-sorted export")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
 
-    return input;
+    /// Runs the interactive prompt when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn in_range(data: &Vec<Data>, date: &str) -> bool {
-    let max_date = convert_date_to_days(data[data.len() - 1].date.as_str());
-    let min_date = convert_date_to_days(data[0].date.as_str());
+#[derive(Subcommand)]
+enum Command {
+    /// Print every record stored at the given date (dd/mm/yyyy)
+    Search {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[arg(short, long, value_enum, default_value_t = SearchAlgorithm::Binary)]
+        algorithm: SearchAlgorithm,
+    },
+    /// Print every record dated from --from to --to, both included
+    Range {
+        #[arg(long)]
+        from: TradeDate,
+
+        #[arg(long)]
+        to: TradeDate,
+    },
+    /// Time every search over generated query workloads and report the
+    /// latency and probe counts
+    Bench {
+        /// Queries per workload
+        #[arg(short, long, default_value_t = 10_000)]
+        queries: usize,
+
+        /// Comma-separated workloads: uniform, from-data, misses, skewed (default: all)
+        #[arg(short, long, value_delimiter = ',')]
+        workload: Vec<Workload>,
+
+        /// Comma-separated searches to run (default: all)
+        #[arg(short, long, value_enum, value_delimiter = ',')]
+        algorithm: Vec<SearchAlgorithm>,
+
+        /// Report format: table, csv or json
+        #[arg(short, long, default_value = "table")]
+        format: ReportFormat,
+
+        /// Seed for the query generator
+        #[arg(long, default_value_t = 0x5EED)]
+        seed: u64,
+
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
 
-    if convert_date_to_days(date) > max_date || convert_date_to_days(date) < min_date {
-        return false;
-    }
+struct BenchOptions {
+    queries: usize,
+    workloads: Vec<Workload>,
+    algorithms: Vec<SearchAlgorithm>,
+    format: ReportFormat,
+    seed: u64,
+}
 
-    return true;
+// Runs the selected searches over the selected workloads and writes the report.
+fn bench(data: &[Data], options: &BenchOptions, writer: impl Write) -> io::Result<()> {
+    let workloads = if options.workloads.is_empty() { Workload::ALL.to_vec() } else { options.workloads.clone() };
+    let searches: Vec<_> = if options.algorithms.is_empty() {
+        trade_core::search::SEARCHES.to_vec()
+    } else {
+        options.algorithms.iter().map(|algorithm| algorithm.search()).collect()
+    };
+    let results = SearchBenchmark::new().queries(options.queries).seed(options.seed).run(data, &searches, &workloads);
+    write_search_report(&results, options.format, writer)
 }
 
-fn print_data_line(data: &Vec<Data>, index: usize) {
-    println!("Index-> {}: {}|{}|{}|{}|{}|{}|{}|{}|{}|{}", 
-        index,
-        data[index].direction, 
-        data[index].year, 
-        data[index].date, 
-        data[index].weekday, 
-        data[index].country, 
-        data[index].comodity, 
-        data[index].transport_mode, 
-        data[index].measure, data[index].value, 
-        data[index].cumulative
-    );
+fn search(data: &[Data], key: TradeDate, algorithm: SearchAlgorithm) -> Option<usize> {
+    if data.is_empty() || !in_range(data, key) {
+        return None;
+    }
+    search_date(algorithm.search(), data, key, &mut SearchStats::default()).ok()
 }
 
 fn main() {
+    let cli = Cli::parse();
+
     let start = SystemTime::now();
-    let data = read_data("cs.csv");
+    let data = match cli.input.load("cs.csv") {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
+        }
+    };
     let end = SystemTime::now();
 
+    if let Some(command) = cli.command {
+        let matches = match command {
+            Command::Search { date, algorithm } => search_all(&data, date, algorithm),
+            Command::Range { from, to } => date_bounds(&data, from..=to),
+            Command::Bench { queries, workload, algorithm, format, seed, output } => {
+                let options = BenchOptions { queries, workloads: workload, algorithms: algorithm, format, seed };
+                let written = match output {
+                    Some(path) => File::create(path).and_then(|file| bench(&data, &options, file)),
+                    None => bench(&data, &options, io::stdout().lock()),
+                };
+                if let Err(e) = written {
+                    eprintln!("Error writing report: {}", e);
+                    process::exit(1);
+                }
+                return;
+            }
+        };
+        if matches.is_empty() {
+            eprintln!("Date not found");
+            process::exit(1);
+        }
+        for index in matches {
+            print_data_line(&data, index);
+        }
+        return;
+    }
+
     println!("Time elapsed: {:?}", end.duration_since(start).unwrap());
-    
-    let input = user_input();
 
-    if !in_range(&data, input.trim()) {
+    let input = prompt("Enter date (dd/mm/yyyy): ");
+
+    let key = match TradeDate::parse(&input) {
+        Ok(key) => key,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    if !in_range(&data, key) {
         println!("Date out of range");
         return;
     }
 
-    // Convert the input date to days for faster comparison later on
-    let date_key = convert_date_to_days(input.trim());
-
+    // Binary search for both ends of the run of records on the date...
     let start = SystemTime::now();
-    let index = binary_search(&data, 0, data.len() - 1, date_key) as usize;
+    let matches = equal_range_by_key(&data, &key, date_key);
     let end = SystemTime::now();
     println!("\nbinary search Done!");
     println!("Time elapsed: {:?}ns", end.duration_since(start).unwrap().as_nanos());
-    
-    print_data_line(&data, index);
 
+    print_matches(&data, matches);
+
+    // ...and interpolation search for one of them, widened to the run.
     let start = SystemTime::now();
-    let index = interpolation_search(&data, 0, data.len() - 1, date_key) as usize;
+    let matches = search_all(&data, key, SearchAlgorithm::Interpolation);
     let end = SystemTime::now();
     println!("\ninterpolation search Done!");
     println!("Time elapsed: {:?}ns", end.duration_since(start).unwrap().as_nanos());
 
-    let index = index;
-    print_data_line(&data, index);
+    print_matches(&data, matches);
 }
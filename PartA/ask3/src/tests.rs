@@ -1,48 +1,117 @@
-#![allow(unused)]
-
-#[cfg(test)]
-
-use crate::Data;
-use crate::convert_date_to_days;
-use crate::read_data;
-use crate::binary_search;
-use crate::interpolation_search;
+use crate::date_key;
+use trade_core::read_data;
+use trade_core::search::{equal_range_by_key, interpolation_search_date};
 use crate::in_range;
+use crate::{bench, search, search_all, BenchOptions, Cli, Command};
+use trade_core::cli::SearchAlgorithm;
+use trade_core::TradeDate;
 
 mod tests {
     use super::*;
 
+    fn date(date_str: &str) -> TradeDate {
+        TradeDate::parse(date_str).unwrap()
+    }
+
     #[test]
-    fn test_convert_date_to_days() {
-        assert_eq!(convert_date_to_days("01/01/2020"), 737331);
-        assert_eq!(convert_date_to_days("31/12/2020"), 737691);
+    fn test_date_key() {
+        let data = read_data("test.csv").unwrap();
+        assert_eq!(date_key(&data[0]), date("01/01/2015"));
+        assert!(date_key(&data[18]) < date_key(&data[19]));
     }
 
     #[test]
     fn test_read_data() {
-        let data = read_data("cs.csv");
-        assert_eq!(data.len(), 111438); // assuming cs.csv has 10 records
-        assert_eq!(data[0].date, "01/01/2015"); // assuming first record date is 01/01/2020
+        let data = read_data("test.csv").unwrap();
+        assert_eq!(data.len(), 22);
+        assert_eq!(data[0].date, "01/01/2015");
     }
 
     #[test]
     fn test_binary_search() {
-        let data = read_data("cs.csv");
-        let index = binary_search(&data, 0, data.len() - 1, convert_date_to_days("01/01/2020"));
-        assert_eq!(data[index].date, "01/01/2020");
+        let data = read_data("test.csv").unwrap();
+        let matches = equal_range_by_key(&data, &date("01/01/2020"), date_key);
+        assert!(!matches.is_empty());
+        assert!(data[matches.clone()].iter().all(|d| d.date == "01/01/2020"));
+        assert_eq!(data[..matches.start].iter().filter(|d| d.date == "01/01/2020").count(), 0);
+        assert!(equal_range_by_key(&data, &date("03/01/2020"), date_key).is_empty());
     }
 
     #[test]
     fn test_interpolation_search() {
-        let data = read_data("cs.csv");
-        let index = interpolation_search(&data, 0, data.len() - 1, convert_date_to_days("31/12/2020"));
+        let data = read_data("test.csv").unwrap();
+        let index = interpolation_search_date(&data, date("31/12/2020")).unwrap();
         assert_eq!(data[index].date, "31/12/2020");
+        assert_eq!(interpolation_search_date(&data, date("01/01/2010")), None);
+        assert_eq!(interpolation_search_date(&data, date("03/01/2020")), None);
+        assert_eq!(interpolation_search_date(&[], date("03/01/2020")), None);
     }
 
     #[test]
     fn test_in_range() {
-        let data = read_data("cs.csv");
-        assert_eq!(in_range(&data, "01/01/2010"), false);
-        assert_eq!(in_range(&data, "01/01/2020"), true);
+        let data = read_data("test.csv").unwrap();
+        assert!(!in_range(&data, date("01/01/2010")));
+        assert!(in_range(&data, date("01/01/2020")));
+    }
+
+    #[test]
+    fn test_search_command() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from([
+            "parta-ask3", "search", "--date", "02/01/2020", "--algorithm", "interpolation", "-i", "test.csv",
+        ])
+        .unwrap();
+        let data = cli.input.load("cs.csv").unwrap();
+        let Some(Command::Search { date: key, algorithm }) = cli.command else {
+            panic!("expected the search subcommand");
+        };
+
+        let index = search(&data, key, algorithm).unwrap();
+        assert_eq!(data[index].date, "02/01/2020");
+        assert_eq!(search(&data, date("03/01/2020"), SearchAlgorithm::Binary), None);
+        assert_eq!(search(&data, date("01/01/2010"), SearchAlgorithm::Binary), None);
+        assert!(Cli::try_parse_from(["parta-ask3", "search", "--date", "31/02/2020"]).is_err());
+    }
+
+    #[test]
+    fn test_search_all_returns_every_record_for_the_date() {
+        let data: Vec<_> = read_data("test.csv").unwrap().into_iter().flat_map(|d| [d.clone(), d.clone(), d]).collect();
+        for algorithm in <SearchAlgorithm as clap::ValueEnum>::value_variants().iter().copied() {
+            let matches = search_all(&data, date("02/01/2020"), algorithm);
+            assert_eq!(matches.len(), 3);
+            assert!(data[matches].iter().all(|d| d.date == "02/01/2020"));
+            assert!(search_all(&data, date("03/01/2020"), algorithm).is_empty());
+        }
+
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["parta-ask3", "range", "--from", "31/12/2020", "--to", "01/01/2021"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Range { .. })));
+    }
+
+    #[test]
+    fn test_bench_command() {
+        use clap::Parser;
+        use trade_core::benchmark::{ReportFormat, Workload};
+
+        let cli = Cli::try_parse_from([
+            "parta-ask3", "bench", "-q", "50", "-w", "from-data,misses", "-a", "binary,fibonacci", "-f", "csv", "-i", "test.csv",
+        ])
+        .unwrap();
+        let data = cli.input.load("cs.csv").unwrap();
+        let Some(Command::Bench { queries, workload, algorithm, format, seed, output: None }) = cli.command else {
+            panic!("expected the bench subcommand");
+        };
+        assert_eq!(workload, [Workload::FromData, Workload::Misses]);
+        assert_eq!(format, ReportFormat::Csv);
+
+        let mut report = Vec::new();
+        bench(&data, &BenchOptions { queries, workloads: workload, algorithms: algorithm, format, seed }, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let rows: Vec<&str> = report.lines().skip(1).collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].starts_with("binary,from-data,50,50,"));
+        assert!(rows[3].starts_with("fibonacci,misses,50,0,"));
+        assert!(Cli::try_parse_from(["parta-ask3", "bench", "-w", "zipf"]).is_err());
     }
 }
@@ -1,172 +1,158 @@
-#[allow(unused)]
-use std::fs::File;
-use std::io::{self, Write};
+use std::ops::RangeBounds;
+use std::process;
 use std::time::SystemTime;
 
-use csv::{ByteRecord, ReaderBuilder};
+use clap::{Parser, Subcommand};
+use trade_core::cli::{InputArgs, SearchAlgorithm};
+use trade_core::search::{self, date_bounds, equal_range_around, range_by_key, search_date, SearchStats, SEARCHES};
+use trade_core::sort::days_to_key;
+use trade_core::{prompt, Data, TradeDate};
 
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-#[allow(unused)]
-#[derive(Debug)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
+// Day number of a date; BIS interpolates on these, so they must be
+// calendar-correct (see `TradeDate`). Only called on loaded records, whose
+// dates the loader has checked.
+fn day_number(date_str: &str) -> i32 {
+    TradeDate::parse(date_str).expect("the loader checks every date").days()
 }
 
-fn date_to_days(date_str: &str) -> u32 {
-    let mut parts = date_str.split('/');
-    let day = parts.next().unwrap().parse::<u32>().unwrap();
-    let month = parts.next().unwrap().parse::<u32>().unwrap();
-    let year = parts.next().unwrap().parse::<u32>().unwrap();
-    
-    year * 365 + month * 30 + day
+// The records dated within `dates`, e.g. `from..=to` or `..=date`: a lower
+// and an upper bound search over the day numbers, so only O(log n) dates are
+// parsed.
+fn in_range(data: &[Data], dates: impl RangeBounds<TradeDate>) -> &[Data] {
+    let key = |date: &TradeDate| days_to_key(date.days());
+    range_by_key(data, (dates.start_bound().map(key), dates.end_bound().map(key)), day_key)
 }
 
-fn read_data(filename: &str) -> Vec<Data> {
-    let file = File::open(filename).expect("Unable to open file");
-    let mut rdr = ReaderBuilder::new()
-        .buffer_capacity(1 << 16) // Set buffer capacity to 64 KB
-        .has_headers(true) // Set this to false if your CSV doesn't have headers
-        .delimiter(b',') // Change this if your CSV uses a different delimiter
-        .quote(b'"') // Change this if your CSV uses a different quoting character
-        .escape(Some(b'\\')) // Change this if your CSV uses a different escape character
-        .double_quote(true) // Set this to false if your CSV doesn't use double quote escaping
-        .flexible(false) // Set this to true if your CSV has a variable number of fields per record
-        .from_reader(file);
-    let mut record = ByteRecord::new();
-    let mut data = Vec::with_capacity(111_438); // Preallocate memory based on an estimate
-
-    while rdr.read_byte_record(&mut record).unwrap() {
-        let direction = String::from_utf8_lossy(&record[0]).into_owned();
-        let year = String::from_utf8_lossy(&record[1]).parse::<u16>().unwrap();
-        let date = String::from_utf8_lossy(&record[2]).into_owned();
-        let weekday = String::from_utf8_lossy(&record[3]).into_owned();
-        let country = String::from_utf8_lossy(&record[4]).into_owned();
-        let comodity = String::from_utf8_lossy(&record[5]).into_owned();
-        let transport_mode = String::from_utf8_lossy(&record[6]).into_owned();
-        let measure = String::from_utf8_lossy(&record[7]).into_owned();
-        let value = String::from_utf8_lossy(&record[8]).parse::<u64>().unwrap();
-        let cumulative = String::from_utf8_lossy(&record[9]).parse::<u64>().unwrap();
-
-        data.push(
-            Data {
-                direction,
-                year,
-                date,
-                weekday,
-                country,
-                comodity,
-                transport_mode,
-                measure,
-                value,
-                cumulative,
-            });
-    }
-
-    data
+fn day_key(data: &Data) -> u64 {
+    days_to_key(day_number(&data.date))
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    print!("Enter date (dd/mm/yyyy): ");
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    return input.trim().to_string();
+// Binary interpolation search over the day numbers: `Ok` holds the index of a
+// record on `date`, `Err` where one would be inserted.
+fn bis(data: &[Data], date: TradeDate, stats: &mut SearchStats) -> Result<usize, usize> {
+    search_date(&search::Bis, data, date, stats)
 }
 
-fn in_range(data: &Vec<Data>, date: &str) -> bool {
-    let max_date = data.iter().map(|x| date_to_days(&x.date)).max().unwrap();
-    let min_date = data.iter().map(|x| date_to_days(&x.date)).min().unwrap();
+// Cost of finding `date` with every search, for `search --stats`.
+fn search_costs(data: &[Data], date: TradeDate) -> [(&'static str, SearchStats); SEARCHES.len()] {
+    SEARCHES.map(|search| {
+        let mut stats = SearchStats::default();
+        let _ = search_date(search, data, date, &mut stats);
+        (search.name(), stats)
+    })
+}
 
-    if date_to_days(date) > max_date || date_to_days(date) < min_date {
-        return false;
-    }
+#[derive(Parser)]
+#[command(about = "Look up trade records by date with binary interpolation search")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
 
-    return true;
+    /// Runs the interactive prompt when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn bis(data: &Vec<Data>, date: &str) -> (bool, usize) {
-    let mut left = 0;
-    let mut right = data.len() - 1;
-    let size = data.len();
-    let target = date_to_days(&date);
+#[derive(Subcommand)]
+enum Command {
+    /// Print every record stored at the given date (dd/mm/yyyy)
+    Search {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[arg(short, long, value_enum, default_value_t = SearchAlgorithm::Bis)]
+        algorithm: SearchAlgorithm,
+
+        /// Also report the probes and jumps every search needs for the date
+        /// (on stderr)
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Print every record dated from --from to --to, both included
+    Range {
+        #[arg(long)]
+        from: TradeDate,
+
+        #[arg(long)]
+        to: TradeDate,
+    },
+}
 
-    let mut next = (size as f32 * (target - date_to_days(&data[left].date)) as f32 / (date_to_days(&data[right].date) - date_to_days(&data[left].date)) as f32).ceil() as usize;
+fn main() {
+    let cli = Cli::parse();
 
-    if next >= size {next = size - 1}
+    let start = SystemTime::now();
+    let data = match cli.input.load("cs.csv") {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
+        }
+    };
+    let end = SystemTime::now();
 
-    while target != date_to_days(&data[next].date) {
-        let mut i = 0;
-        if size <= 3 {
-            // linear search
-            for i in left..right {
-                if date_to_days(&data[i].date) == target {
-                    return (true, i);
+    if let Some(command) = cli.command {
+        let matches = match command {
+            Command::Search { date, algorithm, stats } => {
+                if stats {
+                    for (name, cost) in search_costs(&data, date) {
+                        eprintln!("{}: {} probes, {} jumps", name, cost.probes, cost.jumps);
+                    }
+                }
+                match search_date(algorithm.search(), &data, date, &mut SearchStats::default()) {
+                    // The search lands on one of the records; widen to all of them.
+                    Ok(index) => equal_range_around(&data, index, day_key),
+                    Err(_) => 0..0,
                 }
             }
-            return (false, 0);
+            Command::Range { from, to } => date_bounds(&data, from..=to),
+        };
+        if matches.is_empty() {
+            eprintln!("Date not found");
+            process::exit(1);
         }
-        if target >= date_to_days(&data[next].date) {
-            while target > date_to_days(&data[next + i * ((size as f32).sqrt()) as usize -1].date) {
-                i += 1;
-                right = next + i * ((size as f32).sqrt()) as usize;
-                if right >= size {right = size - 1}
-                left = next + (i - 1) * ((size as f32).sqrt()) as usize;
-            }           
-        } else if target < date_to_days(&data[next].date) {
-            while target < date_to_days(&data[next - i * ((size as f32).sqrt().floor() as usize) + 1].date) {
-                i += 1;
-
-                right = next - (i - 1) * ((size as f32).sqrt()) as usize;
-                if right >= size {right = size - 1}
-
-                left = next - i * ((size as f32).sqrt()) as usize;
-
-                next = left + (((target - date_to_days(&data[left].date)) as f32 / (date_to_days(&data[right].date) - date_to_days(&data[left].date)) as f32) * (right - left + 1) as f32).ceil() as usize - 1;
-                if next < 1 {next = 1}
-            }           
-        } 
-    }
-
-    if target == date_to_days(&data[next].date) {
-        return (true, next);
+        for index in matches {
+            println!("{index}: {}", data[index]);
+        }
+        return;
     }
 
-    return (false, 0);
-}
-fn main() {
-    let start = SystemTime::now();
-    let data = read_data("cs.csv");
-    let end = SystemTime::now();
     println!("Time elapsed: {:?}", end.duration_since(start).unwrap());
 
-    let input = user_input();
+    let input = prompt("Enter date (dd/mm/yyyy): ");
+
+    let date = match TradeDate::parse(&input) {
+        Ok(date) => date,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
 
-    if !in_range(&data, &input) {
+    // Out of range when no record is dated on or before it, or on or after it.
+    if in_range(&data, ..=date).is_empty() || in_range(&data, date..).is_empty() {
         println!("Date out of range");
         return;
     }
 
     let start = SystemTime::now();
-    let (found, index) = bis(&data, &input);
-
-    if found {
-        println!("{index}");
-        println!("{:?}", data[index]);
-        let end = SystemTime::now();
-        println!("{}ns", end.duration_since(start).unwrap().as_nanos());
-    } else {
-        println!("Date not found");
+    let mut stats = SearchStats::default();
+    let found = bis(&data, date, &mut stats);
+
+    match found {
+        Ok(index) => {
+            let matches = equal_range_around(&data, index, day_key);
+            let end = SystemTime::now();
+            for index in matches {
+                println!("{index}: {}", data[index]);
+            }
+            println!("{}ns", end.duration_since(start).unwrap().as_nanos());
+            println!("{} probes, {} jumps", stats.probes, stats.jumps);
+        }
+        Err(_) => println!("Date not found"),
     }
-
 }
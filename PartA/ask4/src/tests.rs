@@ -1,20 +1,20 @@
-#![allow(unused)]
-
-#[cfg(test)]
-
 use crate::Data;
-use crate::date_to_days;
-use crate::read_data;
+use crate::day_number;
 use crate::bis;
 use crate::in_range;
+use crate::{search_costs, Cli, Command};
+use trade_core::cli::SearchAlgorithm;
+use trade_core::search::SearchStats;
+use trade_core::TradeDate;
 
 mod tests {
     use super::*;
 
     #[test]
-    fn test_date_to_days() {
-        assert_eq!(date_to_days("01/01/2020"), 737331);
-        assert_eq!(date_to_days("31/12/2020"), 737691);
+    fn test_day_number() {
+        assert_eq!(day_number("01/01/2020"), 18262);
+        assert_eq!(day_number("31/12/2020"), 18627);
+        assert_eq!(day_number("01/02/2020") - day_number("31/01/2020"), 1);
     }
 
     #[test]
@@ -46,8 +46,13 @@ mod tests {
             }
         ];
 
-        assert_eq!(in_range(&data, "01/01/2019"), true);
-        assert_eq!(in_range(&data, "01/01/2021"), false);
+        let date = |s: &str| TradeDate::parse(s).unwrap();
+        assert_eq!(in_range(&data, date("01/01/2019")..=date("01/01/2019"))[0].direction, "Import");
+        assert_eq!(in_range(&data, ..=date("01/06/2020")).len(), 1);
+        assert_eq!(in_range(&data, date("01/06/2020")..).len(), 1);
+        assert_eq!(in_range(&data, ..).len(), 2);
+        assert!(in_range(&data, date("01/01/2021")..).is_empty());
+        assert!(in_range(&data, date("02/01/2019")..date("31/12/2020")).is_empty());
     }
 
     // assuming that bis function will return first index when date is 01/01/2019
@@ -80,7 +85,59 @@ mod tests {
             }
         ];
 
-        assert_eq!(bis(&data, "01/01/2019"), (true, 0));
-        assert_eq!(bis(&data, "01/01/2021"), (false, 0));
+        let date = |s: &str| TradeDate::parse(s).unwrap();
+        let mut stats = SearchStats::default();
+        assert_eq!(bis(&data, date("01/01/2019"), &mut stats), Ok(0));
+        assert_eq!(bis(&data, date("31/12/2020"), &mut stats), Ok(1));
+        assert_eq!(bis(&data, date("01/01/2021"), &mut stats), Err(2));
+        assert_eq!(bis(&data, date("01/06/2020"), &mut stats), Err(1));
+        assert_eq!(bis(&[], date("01/06/2020"), &mut stats), Err(0));
+        assert!(stats.probes > 0);
+    }
+
+    #[test]
+    fn test_range_command() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["parta-ask4", "range", "--from", "01/01/2019", "--to", "31/12/2020"]).unwrap();
+        let Some(Command::Range { from, to }) = cli.command else {
+            panic!("expected the range subcommand");
+        };
+        assert_eq!((from.to_string().as_str(), to.to_string().as_str()), ("01/01/2019", "31/12/2020"));
+        assert!(Cli::try_parse_from(["parta-ask4", "range", "--from", "01/01/2019"]).is_err());
+    }
+
+    #[test]
+    fn test_bis_on_a_full_year() {
+        // One record per day of 2020, three for every first of the month.
+        let mut data = Vec::new();
+        for day in TradeDate::parse("01/01/2020").unwrap().days()..TradeDate::parse("01/01/2021").unwrap().days() {
+            let date = TradeDate::from_days(day).to_string();
+            let copies = if date.starts_with("01/") { 3 } else { 1 };
+            data.extend(std::iter::repeat_n(Data { date, ..Data::new() }, copies));
+        }
+
+        for record in data.iter().step_by(7) {
+            let date = TradeDate::parse(&record.date).unwrap();
+            let index = bis(&data, date, &mut SearchStats::default()).unwrap();
+            assert_eq!(data[index].date, record.date);
+        }
+
+        let costs = search_costs(&data, TradeDate::parse("17/08/2020").unwrap());
+        let cost = |name| costs.iter().find(|(n, _)| *n == name).unwrap().1;
+        assert_eq!(costs.len(), 7);
+        assert!(cost("bis").probes < cost("binary").probes);
+        assert!(cost("jump").jumps > 0);
+    }
+
+    #[test]
+    fn test_search_stats_flag() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["parta-ask4", "search", "--date", "01/01/2019", "--stats"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Search { stats: true, algorithm: SearchAlgorithm::Bis, .. })));
+
+        let cli = Cli::try_parse_from(["parta-ask4", "search", "--date", "01/01/2019", "-a", "fibonacci"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Search { algorithm: SearchAlgorithm::Fibonacci, .. })));
     }
 }
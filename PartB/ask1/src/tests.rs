@@ -1,24 +1,30 @@
 #![allow(unused)]
 use crate::Data;
-use crate::date_to_days;
-use crate::AvlTree;
-use crate::read_data;
-use crate::height;
+use trade_core::date_to_days;
+use crate::build_tree;
+use trade_core::read_data;
+use crate::{run, TreeCommand};
+use trade_core::date_tree::{DateTree, RecordFilter, SelectError};
+use trade_core::TradeDate;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn date(date_str: &str) -> TradeDate {
+        TradeDate::parse(date_str).unwrap()
+    }
+
     #[test]
     fn test_date_to_days() {
-        assert_eq!(date_to_days("01/01/2020"), Some(737331));
-        assert_eq!(date_to_days("31/12/2020"), Some(737691));
+        assert_eq!(date_to_days("01/01/2020"), Some(18262));
+        assert_eq!(date_to_days("31/12/2020"), Some(18627));
         assert_eq!(date_to_days("invalid date"), None);
     }
 
     #[test]
     fn test_avl_insertion() {
-        let mut tree = AvlTree::new();
+        let mut tree = DateTree::new();
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
@@ -33,8 +39,8 @@ mod tests {
         };
 
         tree.insert(&data);
-        assert_eq!(tree.root.as_ref().unwrap().data, data);
-        assert_eq!(tree.root.as_ref().unwrap().height, 1);
+        assert_eq!(tree.search(data.trade_date().unwrap()).unwrap(), std::slice::from_ref(&data));
+        assert_eq!(tree.height(), 1);
 
         let data2 = Data {
             direction: "Export".to_string(),
@@ -50,13 +56,13 @@ mod tests {
         };
 
         tree.insert(&data2);
-        assert_eq!(tree.root.as_ref().unwrap().right.as_ref().unwrap().data, data2);
-        assert_eq!(tree.root.as_ref().unwrap().height, 2);
+        assert_eq!(tree.records(), [data, data2]);
+        assert_eq!(tree.height(), 2);
     }
 
     #[test]
     fn test_avl_search() {
-        let mut tree = AvlTree::new();
+        let mut tree = DateTree::new();
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
@@ -71,17 +77,17 @@ mod tests {
         };
         tree.insert(&data);
 
-        let search_result = tree.search(&data.date);
+        let search_result = tree.search(data.trade_date().unwrap());
         assert!(search_result.is_some());
-        assert_eq!(search_result.unwrap().data, data);
+        assert_eq!(search_result.unwrap(), [data]);
 
-        let search_result = tree.search("nonexistent key");
+        let search_result = tree.search(date("01/01/2024"));
         assert!(search_result.is_none());
     }
 
     #[test]
     fn test_avl_delete() {
-        let mut tree = AvlTree::new();
+        let mut tree = DateTree::new();
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
@@ -96,15 +102,15 @@ mod tests {
         };
          tree.insert(&data);
 
-        tree.delete(&data.date);
+        assert_eq!(tree.delete(data.trade_date().unwrap(), 0), Some(data.clone()));
 
-        let search_result = tree.search(&data.date);
+        let search_result = tree.search(data.trade_date().unwrap());
         assert!(search_result.is_none());
     }
 
     #[test]
     fn test_avl_edit() {
-        let mut tree = AvlTree::new();
+        let mut tree = DateTree::new();
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
@@ -122,18 +128,183 @@ mod tests {
         println!("Enter new value: ");
 
         let new_value = 5000; // Change the value for the edit operation
-        tree.edit(&data.date, new_value);
+        assert!(tree.edit(data.trade_date().unwrap(), 0, new_value));
+        assert!(!tree.edit(data.trade_date().unwrap(), 1, new_value));
 
-        let search_result = tree.search(&data.date);
+        let search_result = tree.search(data.trade_date().unwrap());
         assert!(search_result.is_some());
-        assert_eq!(search_result.unwrap().data.value, new_value);
+        assert_eq!(search_result.unwrap()[0].value, new_value);
     }
+    #[test]
+    fn test_avl_search_across_month_boundary() {
+        let mut tree = DateTree::new();
+        let january = Data {
+            date: "31/01/2023".to_string(),
+            value: 1,
+            ..Data::new()
+        };
+        let february = Data {
+            date: "01/02/2023".to_string(),
+            value: 2,
+            ..Data::new()
+        };
+        tree.insert(&january);
+        tree.insert(&february);
+
+        assert_eq!(tree.search(date("31/01/2023")).unwrap(), [january]);
+        assert_eq!(tree.search(date("01/02/2023")).unwrap(), [february]);
+        assert!(tree.search(date("1/2/2023")).is_some());
+    }
+
     #[test]
     fn test_read_data_from_csv() {
         // For this, you would ideally have a sample CSV file for testing
-        let tree = read_data("test.csv").unwrap();
+        let tree = build_tree(&read_data("test.csv").unwrap());
         // Insert some assertions based on the data in your test CSV
         // Example:
-        assert_eq!(height(&tree.root), 5); 
+        assert_eq!(tree.height(), 5); 
+    }
+
+    #[test]
+    fn test_tree_commands() {
+        let mut tree = build_tree(&read_data("test.csv").unwrap());
+        let path = std::env::temp_dir().join("partb-ask1-tree.csv");
+        let output = trade_core::cli::OutputArgs { output: Some(path.clone()) };
+
+        let filter = RecordFilter::default();
+        assert!(run(&mut tree, TreeCommand::Edit { date: date("01/01/2015"), value: 7, filter: filter.clone(), output: output.clone() }).is_ok());
+        assert_eq!(tree.search(date("01/01/2015")).unwrap()[0].value, 7);
+        assert_eq!(read_data(path.to_str().unwrap()).unwrap().len(), 18);
+
+        let missing = date("01/01/1990");
+        assert!(run(&mut tree, TreeCommand::Search { date: missing }).is_err());
+        assert!(run(&mut tree, TreeCommand::Delete { date: missing, filter, output }).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_records_sharing_a_date() {
+        let record = |direction: &str, country: &str, value| Data {
+            date: "02/01/2015".to_string(),
+            direction: direction.to_string(),
+            country: country.to_string(),
+            value,
+            ..Data::new()
+        };
+        let mut records = read_data("test.csv").unwrap();
+        records.extend([record("Exports", "China", 1), record("Imports", "China", 2), record("Exports", "Japan", 3)]);
+        let mut tree = build_tree(&records);
+
+        // The date already had one record; all four are found.
+        let on_date = tree.search(date("02/01/2015")).unwrap();
+        assert_eq!(on_date.len(), 4);
+        assert_eq!(tree.records().len(), records.len());
+
+        let china = RecordFilter { country: Some("china".to_string()), ..RecordFilter::default() };
+        assert_eq!(tree.select(date("02/01/2015"), &china), Err(SelectError::Ambiguous(2)));
+        let china_imports = RecordFilter { direction: Some("Imports".to_string()), ..china };
+        assert_eq!(tree.select(date("02/01/2015"), &china_imports), Ok(2));
+        let peru = RecordFilter { country: Some("Peru".to_string()), ..RecordFilter::default() };
+        assert_eq!(tree.select(date("02/01/2015"), &peru), Err(SelectError::NoMatch));
+        assert_eq!(tree.select(date("02/01/1990"), &peru), Err(SelectError::DateNotFound));
+
+        // Edit and delete touch only the chosen record.
+        assert!(tree.edit(date("02/01/2015"), 2, 20));
+        assert_eq!(tree.delete(date("02/01/2015"), 1).unwrap().value, 1);
+        let values: Vec<u64> = tree.search(date("02/01/2015")).unwrap().iter().map(|d| d.value).collect();
+        assert_eq!(values[1..], [20, 3]);
+
+        // Removing the last record of a date removes its node.
+        for _ in 0..3 {
+            assert!(tree.delete(date("02/01/2015"), 0).is_some());
+        }
+        assert!(tree.search(date("02/01/2015")).is_none());
+        assert_eq!(tree.records().len(), records.len() - 4);
+        assert!(tree.delete(date("02/01/2015"), 0).is_none());
+    }
+
+    #[test]
+    fn test_edit_command_needs_a_single_record() {
+        use clap::Parser;
+
+        let mut records = read_data("test.csv").unwrap();
+        let mut copy = records[0].clone();
+        copy.country = "China".to_string();
+        records.push(copy);
+        let mut tree = build_tree(&records);
+
+        let cli = crate::Cli::try_parse_from(["partb-ask1", "tree", "edit", "-d", "01/01/2015", "-v", "9"]).unwrap();
+        let Some(crate::Command::Tree(command)) = cli.command else {
+            panic!("expected the tree subcommand");
+        };
+        assert!(run(&mut tree, command).unwrap_err().starts_with("2 records on that date match"));
+
+        let path = std::env::temp_dir().join("partb-ask1-edit.csv");
+        let cli = crate::Cli::try_parse_from([
+            "partb-ask1", "tree", "edit", "-d", "01/01/2015", "-v", "9", "--country", "China", "-o", path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let Some(crate::Command::Tree(command)) = cli.command else {
+            panic!("expected the tree subcommand");
+        };
+        run(&mut tree, command).unwrap();
+        let values: Vec<u64> = tree.search(date("01/01/2015")).unwrap().iter().map(|d| d.value).collect();
+        assert_eq!(values, [records[0].value, 9]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_date_range_and_neighbours() {
+        let mut records = read_data("test.csv").unwrap();
+        records.push(Data { date: "not a date".to_string(), ..Data::new() });
+        let tree = build_tree(&records);
+
+        let dates: Vec<&str> = tree.range(date("05/01/2015")..=date("08/01/2015")).map(|d| d.date.as_str()).collect();
+        assert_eq!(dates, ["05/01/2015", "06/01/2015", "07/01/2015", "08/01/2015"]);
+        let last = tree.range(date("16/01/2015")..).next_back().unwrap();
+        assert_eq!(last.date, "18/01/2015");
+        // The unreadable date sorts first but stays out of date ranges.
+        assert_eq!(tree.iter().next().unwrap().date, "not a date");
+        assert_eq!(tree.range(..date("03/01/2015")).count(), 2);
+        assert_eq!(tree.range(date("19/01/2015")..).count(), 0);
+
+        let mut tree = tree;
+        tree.delete(date("10/01/2015"), 0);
+        assert_eq!(tree.neighbours(date("10/01/2015")), (Some(date("09/01/2015")), Some(date("11/01/2015"))));
+        assert_eq!(tree.neighbours(date("01/01/2015")), (None, Some(date("02/01/2015"))));
+        let error = run(&mut tree, TreeCommand::Search { date: date("10/01/2015") }).unwrap_err();
+        assert_eq!(error, "Date not found; the closest dates are 09/01/2015 and 11/01/2015");
+    }
+
+    #[test]
+    fn test_period_totals() {
+        let mut records = read_data("test.csv").unwrap();
+        records.push(Data { date: "05/01/2015".to_string(), value: 1, ..Data::new() });
+        records.push(Data { date: "not a date".to_string(), value: u64::MAX, ..Data::new() });
+        let mut tree = build_tree(&records);
+
+        let brute_force = |tree: &DateTree, from, to| {
+            let values: Vec<u64> = tree.iter().filter(|d| d.trade_date().is_ok_and(|d| (from..=to).contains(&d))).map(|d| d.value).collect();
+            (values.iter().map(|&v| v as u128).sum::<u128>(), values.iter().max().copied())
+        };
+        let periods = [("01/01/2015", "18/01/2015"), ("05/01/2015", "05/01/2015"), ("03/01/2015", "11/01/2015"), ("19/01/2015", "31/01/2015")];
+        let check = |tree: &DateTree| {
+            for (from, to) in periods {
+                let (from, to) = (date(from), date(to));
+                assert_eq!((tree.sum_value(from, to), tree.max_value(from, to)), brute_force(tree, from, to), "{}..={}", from, to);
+            }
+        };
+        check(&tree);
+        assert_eq!(tree.max_value(date("19/01/2015"), date("31/01/2015")), None);
+
+        // Edits and deletes keep the stored summaries up to date.
+        assert!(tree.edit(date("07/01/2015"), 0, 10_000_000_000));
+        assert!(tree.delete(date("05/01/2015"), 0).is_some());
+        assert!(tree.delete(date("09/01/2015"), 0).is_some());
+        check(&tree);
+        assert_eq!(tree.max_value(date("01/01/2015"), date("18/01/2015")), Some(10_000_000_000));
+
+        assert!(run(&mut tree, TreeCommand::Totals { from: date("01/01/2015"), to: date("18/01/2015") }).is_ok());
+        assert!(run(&mut tree, TreeCommand::Totals { from: date("01/01/2020"), to: date("02/01/2020") }).is_err());
     }
 }
@@ -1,207 +1,180 @@
-#[allow(unused)]
-use std::fs::File;
-use std::io::Write;
-use std::process::exit;
+use std::process;
 use std::time::SystemTime;
-use std::rc::Rc;
 
-mod tests;
-
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
-}
+use clap::{Parser, Subcommand};
+use trade_core::avl::AvlTree;
+use trade_core::cli::{InputArgs, OutputArgs};
+use trade_core::{print_data, prompt, Data};
 
-#[derive(Debug, Clone)]
-struct Node {
-    data: Rc<Data>,
-    left: Option<Box<Node>>,
-    right: Option<Box<Node>>,
-    height: i32,
-}
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
 
-#[derive(Debug)]
-struct AvlTree {
-    root: Option<Box<Node>>,
+// Records keyed by value, then by the order they were inserted in, so records
+// sharing a value keep their own nodes and the order statistics of the tree
+// count records rather than distinct values.
+#[derive(Debug, Default)]
+struct ValueTree {
+    records: AvlTree<(u64, usize), Data>,
+    inserted: usize,
 }
 
-impl AvlTree {
-    fn new() -> AvlTree {
-        AvlTree { root: None }
+impl ValueTree {
+    fn new() -> ValueTree {
+        ValueTree::default()
     }
 
-    fn insert(&mut self, data: Rc<Data>) {
-        self.root = insert(&mut self.root, data);
+    fn insert(&mut self, data: Data) {
+        self.records.insert((data.value, self.inserted), data);
+        self.inserted += 1;
     }
-}
 
-fn height(node: &Option<Box<Node>>) -> i32 {
-    match node {
-        Some(n) => n.height,
-        None => 0,
+    /// The records with `value`, in insertion order.
+    fn with_value(&self, value: u64) -> impl Iterator<Item = &Data> {
+        self.records.range((value, 0)..=(value, usize::MAX)).map(|(_, data)| data)
     }
-}
-
-fn balance_factor(node: &Node) -> i32 {
-    height(&node.left) - height(&node.right)
-}
 
-fn update_height(node: &mut Box<Node>) {
-    let hl = height(&node.left);
-    let hr = height(&node.right);
-    node.height = std::cmp::max(hl, hr) + 1;
-}
-
-fn rotate_left(mut node: Box<Node>) -> Box<Node> {
-    let mut new_root = node.right.take().unwrap();
-    node.right = new_root.left.take();
-    update_height(&mut node);
-    update_height(&mut new_root);
-    new_root.left = Some(node);
-    new_root
-}
-
-fn rotate_right(mut node: Box<Node>) -> Box<Node> {
-    let mut new_root = node.left.take().unwrap();
-    node.left = new_root.right.take();
-    update_height(&mut node);
-    update_height(&mut new_root);
-    new_root.right = Some(node);
-    new_root
-}
-
-fn balance(mut node: Box<Node>) -> Box<Node> {
-    if balance_factor(&node) > 1 {
-        if balance_factor(&node.left.as_ref().unwrap()) < 0 {
-            node.left = Some(rotate_left(node.left.unwrap()));
-        }
-        return rotate_right(node);
-    }
-    if balance_factor(&node) < -1 {
-        if balance_factor(&node.right.as_ref().unwrap()) > 0 {
-            node.right = Some(rotate_right(node.right.unwrap()));
-        }
-        return rotate_left(node);
+    /// The record with `k` records of smaller (or equal, earlier inserted)
+    /// value before it.
+    fn nth(&self, k: usize) -> Option<&Data> {
+        self.records.select(k).map(|(_, data)| data)
     }
-    update_height(&mut node); // Moved here to avoid redundant height updates
-    node
-}
 
-fn insert(root: &mut Option<Box<Node>>, data: Rc<Data>) -> Option<Box<Node>> {
-    if root.is_none() {
-        return Some(Box::new(Node {
-            data: data, // No need to clone
-            left: None,
-            right: None,
-            height: 0,
-        }));
-    }
-    if data.value < root.as_ref().unwrap().data.value {
-        root.as_mut().unwrap().left = insert(&mut root.as_mut().unwrap().left, data.clone());
-    } else {
-        root.as_mut().unwrap().right = insert(&mut root.as_mut().unwrap().right, data.clone());
+    /// Number of records with a value below `value`.
+    fn count_below(&self, value: u64) -> usize {
+        self.records.rank(&(value, 0))
     }
-    Some(balance(root.take().unwrap()))
-}
 
-fn node_with_min_value(root: &Option<Box<Node>>) -> Option<Box<Node>> {
-    let mut current = root.as_ref().unwrap();
-    while let Some(node) = &current.left {
-        current = node;
+    /// Number of records with a value from `low` to `high`, both included.
+    fn count_between(&self, low: u64, high: u64) -> usize {
+        self.records.count_in_range((low, 0)..=(high, usize::MAX))
     }
-    return Some(current.clone());
 }
 
-fn node_with_max_value(root: &Option<Box<Node>>) -> Option<Box<Node>> {
-    let mut current = root.as_ref().unwrap();
-    while let Some(node) = &current.right {
-        current = node;
-    }
-    return Some(current.clone());
-}
+fn build_tree(records: Vec<Data>) -> ValueTree {
+    let mut tree = ValueTree::new();
 
-fn nodes_with_same_value<'a>(root: &'a Option<Box<Node>>, value: &u64, nodes: &mut Vec<&'a Node>) {
-    if let Some(ref node) = *root {
-        if &node.data.value == value {
-            nodes.push(node.as_ref());
-        }
-        nodes_with_same_value(&node.left, value, nodes);
-        nodes_with_same_value(&node.right, value, nodes);
+    for data in records {
+        tree.insert(data);
     }
+
+    tree
 }
 
-fn read_data(filename: &str) -> Option<AvlTree> {
-    let mut reader = match csv::Reader::from_path(filename) {
-        Ok(reader) => reader,
-        Err(_) => {
-            println!("Error reading file");
-            exit(1);
-        }
+// All records sharing the largest (or smallest) value, at most `limit` of them.
+fn records_with_extreme_value(tree: &ValueTree, max: bool, limit: usize) -> Vec<Data> {
+    let entry = if max {
+        tree.records.last_key_value()
+    } else {
+        tree.records.first_key_value()
     };
-    let mut tree = AvlTree::new();
 
-    for result in reader.records() {
-        let record = match result {
-            Ok(record) => record,
-            Err(_) => {
-                println!("Error reading record");
-                exit(1);
-            }
-        };
-        let data = Rc::new(Data {
-            direction: record.get(0).unwrap().to_string(),
-            year: record.get(1).unwrap().parse::<u16>().unwrap(),
-            date: record.get(2).unwrap().to_string(),
-            weekday: record.get(3).unwrap().to_string(),
-            country: record.get(4).unwrap().to_string(),
-            comodity: record.get(5).unwrap().to_string(),
-            transport_mode: record.get(6).unwrap().to_string(),
-            measure: record.get(7).unwrap().to_string(),
-            value: record.get(8).unwrap().parse::<u64>().unwrap(),
-            cumulative: record.get(9).unwrap().parse::<u64>().unwrap(),
-        });
-
-        tree.insert(data);
+    match entry {
+        Some((&(value, _), _)) => tree.with_value(value).take(limit).cloned().collect(),
+        None => Vec::new(),
     }
-
-    return Some(tree);
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
-}
+#[derive(Parser)]
+#[command(about = "Value-keyed AVL tree over the trade records")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Runs the interactive menu when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Query the value-keyed AVL tree
+    #[command(subcommand)]
+    Tree(TreeCommand),
+}
+
+#[derive(Subcommand)]
+enum TreeCommand {
+    /// Records with the largest value
+    Max {
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Records with the smallest value
+    Min {
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// The record with the k-th smallest value (1 is the smallest)
+    Nth {
+        #[arg(short, value_parser = clap::value_parser!(u64).range(1..))]
+        k: u64,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Count the records with a value below --value
+    Rank {
+        #[arg(short, long)]
+        value: u64,
+    },
+    /// Count the records with a value from --min to --max, both included
+    Count {
+        #[arg(long)]
+        min: u64,
+
+        #[arg(long)]
+        max: u64,
+    },
+}
+
+fn run(tree: &ValueTree, command: TreeCommand) -> Result<(), String> {
+    let (records, output) = match command {
+        TreeCommand::Max { limit, output } => (records_with_extreme_value(tree, true, limit), output),
+        TreeCommand::Min { limit, output } => (records_with_extreme_value(tree, false, limit), output),
+        TreeCommand::Nth { k, output } => {
+            let data = tree.nth(k as usize - 1).ok_or_else(|| format!("There are only {} records", tree.records.len()))?;
+            (vec![data.clone()], output)
+        }
+        TreeCommand::Rank { value } => {
+            println!("{}", tree.count_below(value));
+            return Ok(());
+        }
+        TreeCommand::Count { min, max } => {
+            println!("{}", tree.count_between(min, max));
+            return Ok(());
+        }
+    };
 
-fn print_data(data: &Data) {
-    println!(
-        "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
-        data.direction,
-        data.year,
-        data.date,
-        data.weekday,
-        data.country,
-        data.comodity,
-        data.transport_mode,
-        data.measure,
-        data.value,
-        data.cumulative
-    );
+    output.write(&records).map_err(|e| e.to_string())
 }
 
 fn main() {
+    let cli = Cli::parse();
+
     let start = SystemTime::now();
-    let root = read_data("effects.csv");
+    let root = match cli.input.load("effects.csv") {
+        Ok(records) => build_tree(records),
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(Command::Tree(command)) = cli.command {
+        if let Err(e) = run(&root, command) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     println!("Time elapsed: {:?}", start.elapsed().unwrap());
     
     loop {
@@ -210,40 +183,22 @@ fn main() {
         println!("2. find the data with the MIN value");
         println!("0. exit");
         println!("---------------------------");
-        print!("Enter your choice: ");
-        std::io::stdout().flush().unwrap();
-        let choice = user_input();
+        let choice = prompt("Enter your choice: ");
 
         match choice.as_str() {
             "1" => {
-                let node = node_with_max_value(&root.as_ref().unwrap().root);
-                match node {
-                    Some(node) => {
-                        let mut nodes = Vec::new();
-                        nodes_with_same_value(&root.as_ref().unwrap().root, &node.data.value, &mut nodes);
-                        nodes.into_iter().take(10).for_each(|node| {
-                            print_data(&node.data);
-                        });
-                    }
-                    None => {
-                        println!("No data found");
-                    }
+                let records = records_with_extreme_value(&root, true, 10);
+                if records.is_empty() {
+                    println!("No data found");
                 }
+                records.iter().for_each(print_data);
             }
             "2" => {
-                let node = node_with_min_value(&root.as_ref().unwrap().root);
-                match node {
-                    Some(node) => {
-                        let mut nodes = Vec::new();
-                        nodes_with_same_value(&root.as_ref().unwrap().root, &node.data.value, &mut nodes);
-                        nodes.into_iter().take(10).for_each(|node| {
-                            print_data(&node.data);
-                        });
-                    }
-                    None => {
-                        println!("No data found");
-                    }
+                let records = records_with_extreme_value(&root, false, 10);
+                if records.is_empty() {
+                    println!("No data found");
                 }
+                records.iter().for_each(print_data);
             }
             "0" => {
                 break;
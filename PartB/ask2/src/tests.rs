@@ -1,24 +1,21 @@
 #![allow(unused)]
 
-use std::rc::Rc;
-
 use crate::Data;
-use crate::AvlTree;
-use crate::height;
-use crate::node_with_max_value;
-use crate::node_with_min_value;
-use crate::nodes_with_same_value;
-use crate:: read_data;
+use crate::ValueTree;
+use crate::build_tree;
+use crate::records_with_extreme_value;
+use crate::{run, TreeCommand};
+use trade_core::read_data;
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_sample_tree() -> AvlTree {
-        let mut tree = AvlTree::new();
+    fn create_sample_tree() -> ValueTree {
+        let mut tree = ValueTree::new();
 
-        let data1 = Rc::new(Data {
+        let data1 = Data {
             direction: "import".to_string(),
             year: 2021,
             date: "01-01-2021".to_string(),
@@ -29,9 +26,9 @@ mod tests {
             measure: "ton".to_string(),
             value: 5,
             cumulative: 5,
-        });
+        };
 
-        let data2 = Rc::new(Data {
+        let data2 = Data {
             direction: "import".to_string(),
             year: 2021,
             date: "01-01-2021".to_string(),
@@ -42,7 +39,7 @@ mod tests {
             measure: "ton".to_string(),
             value: 3,
             cumulative: 5,
-        });
+        };
 
         // ... you can create more data samples if needed
 
@@ -56,39 +53,76 @@ mod tests {
     #[test]
     fn test_insert_and_balance() {
         let tree = create_sample_tree();
-        assert_eq!(height(&tree.root), 1); // This might change based on your data
+        assert_eq!(tree.records.height(), 2); // This might change based on your data
         // Add more assertions based on the data you've inserted
     }
 
     #[test]
     fn test_max_value() {
         let tree = create_sample_tree();
-        let max_node = node_with_max_value(&tree.root);
-        assert_eq!(max_node.unwrap().data.value, 5);
+        let (&(max, _), data) = tree.records.last_key_value().unwrap();
+        assert_eq!(max, 5);
+        assert_eq!(data.value, 5);
     }
 
     #[test]
     fn test_min_value() {
         let tree = create_sample_tree();
-        let min_node = node_with_min_value(&tree.root);
-        assert_eq!(min_node.unwrap().data.value, 3);
+        let (&(min, _), _) = tree.records.first_key_value().unwrap();
+        assert_eq!(min, 3);
     }
 
     #[test]
     fn test_nodes_with_value() {
         let tree = create_sample_tree();
-        let mut nodes = Vec::new();
-        nodes_with_same_value(&tree.root, &5, &mut nodes);
-        assert_eq!(nodes.len(), 1); // If you've only inserted one node with value 5
-        assert_eq!(nodes[0].data.value, 5);
+        let records: Vec<_> = tree.with_value(5).collect();
+        assert_eq!(records.len(), 1); // If you've only inserted one record with value 5
+        assert_eq!(records[0].value, 5);
     }
 
     #[test]
     fn test_read_data_from_csv() {
         // For this, you would ideally have a sample CSV file for testing
-        let tree = read_data("test.csv").unwrap();
+        let tree = build_tree(read_data("test.csv").unwrap());
         // Insert some assertions based on the data in your test CSV
         // Example:
-        assert_eq!(height(&tree.root), 4); 
+        assert_eq!(tree.records.height(), 5); 
+    }
+
+    #[test]
+    fn test_records_with_extreme_value() {
+        let tree = create_sample_tree();
+        let max = records_with_extreme_value(&tree, true, 10);
+        let min = records_with_extreme_value(&tree, false, 10);
+        assert_eq!(max.len(), 1);
+        assert!(min.iter().all(|d| d.value <= max[0].value));
+        assert!(records_with_extreme_value(&tree, true, 0).is_empty());
+        assert!(records_with_extreme_value(&ValueTree::new(), false, 10).is_empty());
+    }
+
+    #[test]
+    fn test_order_statistics() {
+        let mut records = read_data("test.csv").unwrap();
+        let mut values: Vec<u64> = records.iter().map(|d| d.value).collect();
+        values.sort_unstable();
+        // A repeated value counts once per record.
+        records.push(records[3].clone());
+        values.insert(values.partition_point(|&v| v <= records[3].value), records[3].value);
+        let tree = build_tree(records.clone());
+
+        for (k, &value) in values.iter().enumerate() {
+            assert_eq!(tree.nth(k).unwrap().value, value);
+            assert_eq!(tree.count_below(value), values.partition_point(|&v| v < value));
+        }
+        assert!(tree.nth(values.len()).is_none());
+        assert_eq!(tree.with_value(records[3].value).count(), 2);
+        let (low, high) = (values[2], values[values.len() - 3]);
+        assert_eq!(tree.count_between(low, high), values.iter().filter(|&&v| (low..=high).contains(&v)).count());
+        assert_eq!(tree.count_between(high, low), 0);
+
+        assert!(run(&tree, TreeCommand::Nth { k: values.len() as u64 + 1, output: trade_core::cli::OutputArgs { output: None } }).is_err());
+        use clap::Parser;
+        assert!(crate::Cli::try_parse_from(["partb-ask2", "tree", "nth", "-k", "0"]).is_err());
+        assert!(crate::Cli::try_parse_from(["partb-ask2", "tree", "count", "--min", "5", "--max", "9"]).is_ok());
     }
 }
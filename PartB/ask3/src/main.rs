@@ -1,36 +1,28 @@
-#[allow(unused)]
-use std::fs::File;
-use std::io::Write;
-use std::process::exit;
+use std::process;
 use std::time::SystemTime;
 
+use clap::{Parser, Subcommand};
+use trade_core::cli::{InputArgs, OutputArgs};
+use trade_core::{print_data, prompt, Data, TradeDate};
+
 const MOD: usize = 11;
 
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
-}
-
 #[derive(Debug, Clone)]
 struct Node {
+    // Parsed once on insert; a record whose date does not parse never matches.
+    date: Option<TradeDate>,
     data: Data,
     next: Option<Box<Node>>
 }
 
 impl Node {
-    fn new(data: Data) -> Node {
+    fn new(date: Option<TradeDate>, data: Data) -> Node {
         Node {
+            date,
             data,
             next: None
         }
@@ -51,8 +43,8 @@ impl LinkedList {
         }
     }
 
-    fn push_back(&mut self, data: Data) {
-        let mut new_node = Box::new(Node::new(data));
+    fn push_back(&mut self, date: Option<TradeDate>, data: Data) {
+        let mut new_node = Box::new(Node::new(date, data));
         new_node.next = None;
 
         match self.last {
@@ -80,24 +72,23 @@ fn init() -> Vec<LinkedList> {
     hash_table
 }
 
-fn hash(date: &str) -> usize {
-    let mut sum = 0;
-    for c in date.chars() {
-        sum += c as usize;
-    }
-    sum % MOD
+// Hashes the day number, so `1/2/2020` and `01/02/2020` share a bucket.
+fn hash(date: Option<TradeDate>) -> usize {
+    date.map_or(0, |date| date.days().rem_euclid(MOD as i32) as usize)
 }
 
-fn insert(hash_table: &mut Vec<LinkedList>, data: Data) {
-    let index = hash(&data.date);
-    hash_table[index].push_back(data);
+fn insert(hash_table: &mut [LinkedList], data: Data) {
+    let date = data.trade_date().ok();
+    let index = hash(date);
+    hash_table[index].push_back(date, data);
 }
 
-fn search(hash_table: &Vec<LinkedList>, date: &str) -> Option<Box<Node>> {
+fn search(hash_table: &[LinkedList], date: TradeDate) -> Option<Box<Node>> {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.clone();
     while let Some(node) = current {
-        if node.data.date == date {
+        if node.date == date {
             return Some(node);
         }
         current = node.next;
@@ -105,12 +96,13 @@ fn search(hash_table: &Vec<LinkedList>, date: &str) -> Option<Box<Node>> {
     None
 }
 
-fn edit(hash_table: &mut Vec<LinkedList>, date: &str, data: Data) {
+fn edit(hash_table: &mut [LinkedList], date: TradeDate, data: Data) {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.as_mut();
 
     while let Some(node) = current {
-        if node.data.date == date {
+        if node.date == date {
             node.data = data;
             return;
         }
@@ -118,7 +110,8 @@ fn edit(hash_table: &mut Vec<LinkedList>, date: &str, data: Data) {
     }
 }
 
-fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
+fn delete(hash_table: &mut [LinkedList], date: TradeDate) {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.as_mut();
 
@@ -128,7 +121,7 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     }
 
     // If the date to be deleted is at the head of the linked list.
-    if current.as_ref().unwrap().data.date == date {
+    if current.as_ref().unwrap().date == date {
         hash_table[index].first = current.take().unwrap().next.take();
         return;
     }
@@ -136,7 +129,7 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     // Check the rest of the linked list.
     while let Some(node) = current {
         if let Some(next_node) = node.next.as_mut() {
-            if next_node.data.date == date {
+            if next_node.date == date {
                 // Node to be deleted is next_node.
                 node.next = next_node.next.take();
                 return;
@@ -146,71 +139,32 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     }
 }
 
-fn read_data(filename: &str) -> Vec<LinkedList> {
-    let mut reader = match csv::Reader::from_path(filename) {
-        Ok(reader) => reader,
-        Err(_) => {
-            println!("Error reading file");
-            exit(1);
-        }
-    };
+fn build_table(records: Vec<Data>) -> Vec<LinkedList> {
     let mut vec = init();
 
-    for result in reader.records() {
-        let record = match result {
-            Ok(record) => record,
-            Err(_) => {
-                println!("Error reading record");
-                exit(1);
-            }
-        };
-        let data = Data {
-            direction: record[0].to_string(),
-            year: record[1].parse::<u16>().unwrap(),
-            date: record[2].to_string(),
-            weekday: record[3].to_string(),
-            country: record[4].to_string(),
-            comodity: record[5].to_string(),
-            transport_mode: record[6].to_string(),
-            measure: record[7].to_string(),
-            value: record[8].parse::<u64>().unwrap(),
-            cumulative: record[9].parse::<u64>().unwrap(),
-        };
-
-        // println!("{:?}", data.date);
-
+    for data in records {
         insert(&mut vec, data);
     }
 
-    return vec;
+    vec
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
-}
-
-fn print_data(data: &Data) {
-    println!(
-        "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
-        data.direction,
-        data.year,
-        data.date,
-        data.weekday,
-        data.country,
-        data.comodity,
-        data.transport_mode,
-        data.measure,
-        data.value,
-        data.cumulative
-    );
+// Every record in the table, bucket by bucket.
+fn records(vec: &[LinkedList]) -> Vec<Data> {
+    let mut records = Vec::new();
+    for list in vec {
+        let mut current = list.first.as_ref();
+        while let Some(node) = current {
+            records.push(node.data.clone());
+            current = node.next.as_ref();
+        }
+    }
+    records
 }
 
 // fn to print the vector of linked list
 #[allow(dead_code)]
-fn print_vec(vec: &Vec<LinkedList>) {
+fn print_vec(vec: &[LinkedList]) {
     for (i, list) in vec.iter().enumerate() {
         println!("{}: ", i);
         let mut current = list.first.clone();
@@ -221,9 +175,97 @@ fn print_vec(vec: &Vec<LinkedList>) {
     }
 }
 
+#[derive(Parser)]
+#[command(about = "Date-keyed hash table over the trade records")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Runs the interactive menu when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Query or modify the date-keyed hash table
+    #[command(subcommand)]
+    Hash(HashCommand),
+}
+
+#[derive(Subcommand)]
+enum HashCommand {
+    /// Print the record stored at a date (dd/mm/yyyy)
+    Search {
+        #[arg(short, long)]
+        date: TradeDate,
+    },
+    /// Set the value of the record at a date, then print or save the table
+    Edit {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[arg(short, long)]
+        value: u64,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete the record at a date, then print or save the table
+    Delete {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+fn run(vec: &mut [LinkedList], command: HashCommand) -> Result<(), String> {
+    let output = match command {
+        HashCommand::Search { date } => {
+            let node = search(vec, date).ok_or("No data found")?;
+            print_data(&node.data);
+            return Ok(());
+        }
+        HashCommand::Edit { date, value, output } => {
+            let mut data = search(vec, date).ok_or("No data found")?.data;
+            data.value = value;
+            edit(vec, date, data);
+            output
+        }
+        HashCommand::Delete { date, output } => {
+            if search(vec, date).is_none() {
+                return Err("No data found".to_string());
+            }
+            delete(vec, date);
+            output
+        }
+    };
+
+    output.write(&records(vec)).map_err(|e| e.to_string())
+}
+
 fn main() {
+    let cli = Cli::parse();
+
     let start = SystemTime::now();
-    let mut vec = read_data("effects.csv");
+    let mut vec = match cli.input.load("effects.csv") {
+        Ok(records) => build_table(records),
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(Command::Hash(command)) = cli.command {
+        if let Err(e) = run(&mut vec, command) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     println!("Time elapsed: {:?}", start.elapsed().unwrap());
 
     // print_vec(&vec);
@@ -234,17 +276,18 @@ fn main() {
         println!("2. Edit");
         println!("3. Delete");
         println!("0. Exit");
-        print!("Enter your choice: ");
-        std::io::stdout().flush().unwrap();
-
-        let choice = user_input();
+        let choice = prompt("Enter your choice: ");
 
         match choice.as_str() {
             "1" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                let node = match search(&vec, &date) {
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let node = match search(&vec, date) {
                     Some(node) => node,
                     None => {
                         println!("No data found");
@@ -254,28 +297,34 @@ fn main() {
                 print_data(&node.data);
             }
             "2" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                let node = match search(&vec, &date) {
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let node = match search(&vec, date) {
                     Some(node) => node,
                     None => {
                         println!("No data found");
                         continue;
                     }
                 };
-                println!("Enter new value: ");
-                std::io::stdout().flush().unwrap();
-                let value = user_input();
+                let value = prompt("Enter new value: ");
                 let mut data = node.data;
                 data.value = value.parse::<u64>().unwrap();
-                edit(&mut vec, &date, data);
+                edit(&mut vec, date, data);
             }
             "3" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                delete(&mut vec, &date);
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                delete(&mut vec, date);
                 // print_vec(&vec);
             }
             "0" => {
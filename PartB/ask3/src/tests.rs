@@ -6,15 +6,23 @@ use crate::insert;
 use crate::search;
 use crate::edit;
 use crate::delete;
+use crate::{build_table, records, run, HashCommand};
+use trade_core::{read_data, TradeDate};
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Looked up zero-padded, while the records store it as `13/8/2023`.
+    fn date() -> TradeDate {
+        TradeDate::parse("13/08/2023").unwrap()
+    }
+
     #[test]
     fn test_hash() {
-        assert_eq!(hash("2023-08-13"), hash("2023-08-13"));
-        assert_ne!(hash("2023-08-13"), hash("2023-08-14"));
+        let date = TradeDate::parse("13/08/2023").ok();
+        assert_eq!(hash(date), hash(TradeDate::parse("13/8/2023").ok()));
+        assert_ne!(hash(date), hash(TradeDate::parse("14/08/2023").ok()));
     }
 
     #[test]
@@ -23,7 +31,7 @@ mod tests {
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
-            date: "2023-08-13".to_string(),
+            date: "13/8/2023".to_string(),
             weekday: "Monday".to_string(),
             country: "CountryX".to_string(),
             comodity: "Oil".to_string(),
@@ -35,8 +43,8 @@ mod tests {
 
         insert(&mut hash_table, data.clone());
 
-        let found_node = search(&hash_table, "2023-08-13").unwrap();
-        assert_eq!(found_node.data.date, "2023-08-13");
+        let found_node = search(&hash_table, date()).unwrap();
+        assert_eq!(found_node.data.date, "13/8/2023");
         assert_eq!(found_node.data.value, 1000);
     }
 
@@ -46,7 +54,7 @@ mod tests {
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
-            date: "2023-08-13".to_string(),
+            date: "13/8/2023".to_string(),
             weekday: "Monday".to_string(),
             country: "CountryX".to_string(),
             comodity: "Oil".to_string(),
@@ -60,9 +68,9 @@ mod tests {
 
         let mut new_data = data.clone();
         new_data.value = 1500;
-        edit(&mut hash_table, "2023-08-13", new_data.clone());
+        edit(&mut hash_table, date(), new_data.clone());
 
-        let found_node = search(&hash_table, "2023-08-13").unwrap();
+        let found_node = search(&hash_table, date()).unwrap();
         assert_eq!(found_node.data.value, 1500);
     }
 
@@ -72,7 +80,7 @@ mod tests {
         let data = Data {
             direction: "Import".to_string(),
             year: 2023,
-            date: "2023-08-13".to_string(),
+            date: "13/8/2023".to_string(),
             weekday: "Monday".to_string(),
             country: "CountryX".to_string(),
             comodity: "Oil".to_string(),
@@ -84,11 +92,24 @@ mod tests {
 
         insert(&mut hash_table, data.clone());
 
-        let found_node = search(&hash_table, "2023-08-13").unwrap();
-        assert_eq!(found_node.data.date, "2023-08-13");
+        let found_node = search(&hash_table, date()).unwrap();
+        assert_eq!(found_node.data.date, "13/8/2023");
+
+        delete(&mut hash_table, date());
+        let found_node_after_delete = search(&hash_table, date());
+        assert!(found_node_after_delete.is_none());
+    }
+
+    #[test]
+    fn test_hash_commands() {
+        let mut vec = build_table(read_data("test.csv").unwrap());
+        let date = trade_core::TradeDate::parse("02/01/2015").unwrap();
+        let output = trade_core::cli::OutputArgs { output: None };
 
-        delete(&mut hash_table, "2023-08-13");
-        let found_node_after_delete = search(&hash_table, "2023-08-13");
-        assert_eq!(found_node_after_delete.is_none(), true);
+        assert!(run(&mut vec, HashCommand::Search { date }).is_ok());
+        assert!(run(&mut vec, HashCommand::Delete { date, output }).is_ok());
+        assert!(search(&vec, date).is_none());
+        assert_eq!(records(&vec).len(), 17);
+        assert!(run(&mut vec, HashCommand::Search { date }).is_err());
     }
 }
@@ -1,34 +1,23 @@
-#[allow(unused)]
-use std::fs::File;
-use std::io::Write;
-use std::process::exit;
 use std::time::SystemTime;
 
-const MOD: usize = 11;
+use clap::Subcommand;
+use trade_core::cli::{InputArgs, OutputArgs};
+use trade_core::{print_data, prompt, Data, TradeDate};
 
-#[derive(Debug, Clone)]
-struct Data {
-    direction: String,
-    year: u16,
-    date: String,
-    weekday: String,
-    country: String,
-    comodity: String,
-    transport_mode: String,
-    measure: String,
-    value: u64,
-    cumulative: u64,
-}
+const MOD: usize = 11;
 
 #[derive(Debug, Clone)]
 struct Node {
+    // Parsed once on insert; a record whose date does not parse never matches.
+    date: Option<TradeDate>,
     data: Data,
     next: Option<Box<Node>>
 }
 
 impl Node {
-    fn new(data: Data) -> Node {
+    fn new(date: Option<TradeDate>, data: Data) -> Node {
         Node {
+            date,
             data,
             next: None
         }
@@ -49,8 +38,8 @@ impl LinkedList {
         }
     }
 
-    fn push_back(&mut self, data: Data) {
-        let mut new_node = Box::new(Node::new(data));
+    fn push_back(&mut self, date: Option<TradeDate>, data: Data) {
+        let mut new_node = Box::new(Node::new(date, data));
         new_node.next = None;
 
         match self.last {
@@ -78,24 +67,23 @@ fn init() -> Vec<LinkedList> {
     hash_table
 }
 
-fn hash(date: &str) -> usize {
-    let mut sum = 0;
-    for c in date.chars() {
-        sum += c as usize;
-    }
-    sum % MOD
+// Hashes the day number, so `1/2/2020` and `01/02/2020` share a bucket.
+fn hash(date: Option<TradeDate>) -> usize {
+    date.map_or(0, |date| date.days().rem_euclid(MOD as i32) as usize)
 }
 
-fn insert(hash_table: &mut Vec<LinkedList>, data: Data) {
-    let index = hash(&data.date);
-    hash_table[index].push_back(data);
+fn insert(hash_table: &mut [LinkedList], data: Data) {
+    let date = data.trade_date().ok();
+    let index = hash(date);
+    hash_table[index].push_back(date, data);
 }
 
-fn search(hash_table: &Vec<LinkedList>, date: &str) -> Option<Box<Node>> {
+fn search(hash_table: &[LinkedList], date: TradeDate) -> Option<Box<Node>> {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.clone();
     while let Some(node) = current {
-        if node.data.date == date {
+        if node.date == date {
             return Some(node);
         }
         current = node.next;
@@ -103,12 +91,13 @@ fn search(hash_table: &Vec<LinkedList>, date: &str) -> Option<Box<Node>> {
     None
 }
 
-fn edit(hash_table: &mut Vec<LinkedList>, date: &str, data: Data) {
+fn edit(hash_table: &mut [LinkedList], date: TradeDate, data: Data) {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.as_mut();
 
     while let Some(node) = current {
-        if node.data.date == date {
+        if node.date == date {
             node.data = data;
             return;
         }
@@ -116,7 +105,8 @@ fn edit(hash_table: &mut Vec<LinkedList>, date: &str, data: Data) {
     }
 }
 
-fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
+fn delete(hash_table: &mut [LinkedList], date: TradeDate) {
+    let date = Some(date);
     let index = hash(date);
     let mut current = hash_table[index].first.as_mut();
 
@@ -126,7 +116,7 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     }
 
     // If the date to be deleted is at the head of the linked list.
-    if current.as_ref().unwrap().data.date == date {
+    if current.as_ref().unwrap().date == date {
         hash_table[index].first = current.take().unwrap().next.take();
         return;
     }
@@ -134,7 +124,7 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     // Check the rest of the linked list.
     while let Some(node) = current {
         if let Some(next_node) = node.next.as_mut() {
-            if next_node.data.date == date {
+            if next_node.date == date {
                 // Node to be deleted is next_node.
                 node.next = next_node.next.take();
                 return;
@@ -144,71 +134,31 @@ fn delete(hash_table: &mut Vec<LinkedList>, date: &str) {
     }
 }
 
-fn read_data(filename: &str) -> Vec<LinkedList> {
-    let mut reader = match csv::Reader::from_path(filename) {
-        Ok(reader) => reader,
-        Err(_) => {
-            println!("Error reading file");
-            exit(1);
-        }
-    };
+fn build_table(records: Vec<Data>) -> Vec<LinkedList> {
     let mut vec = init();
 
-    for result in reader.records() {
-        let record = match result {
-            Ok(record) => record,
-            Err(_) => {
-                println!("Error reading record");
-                exit(1);
-            }
-        };
-        let data = Data {
-            direction: record[0].to_string(),
-            year: record[1].parse::<u16>().unwrap(),
-            date: record[2].to_string(),
-            weekday: record[3].to_string(),
-            country: record[4].to_string(),
-            comodity: record[5].to_string(),
-            transport_mode: record[6].to_string(),
-            measure: record[7].to_string(),
-            value: record[8].parse::<u64>().unwrap(),
-            cumulative: record[9].parse::<u64>().unwrap(),
-        };
-
-        // println!("{:?}", data.date);
-
+    for data in records {
         insert(&mut vec, data);
     }
 
-    return vec;
+    vec
 }
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
-}
-
-fn print_data(data: &Data) {
-    println!(
-        "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
-        data.direction,
-        data.year,
-        data.date,
-        data.weekday,
-        data.country,
-        data.comodity,
-        data.transport_mode,
-        data.measure,
-        data.value,
-        data.cumulative
-    );
+// Every record in the table, bucket by bucket.
+fn records(vec: &[LinkedList]) -> Vec<Data> {
+    let mut records = Vec::new();
+    for list in vec {
+        let mut current = list.first.as_ref();
+        while let Some(node) = current {
+            records.push(node.data.clone());
+            current = node.next.as_ref();
+        }
+    }
+    records
 }
 
 // fn to print the vector of linked list
-#[allow(dead_code)]
-fn print_vec(vec: &Vec<LinkedList>) {
+fn print_vec(vec: &[LinkedList]) {
     for (i, list) in vec.iter().enumerate() {
         println!("{}: ", i);
         let mut current = list.first.clone();
@@ -219,9 +169,75 @@ fn print_vec(vec: &Vec<LinkedList>) {
     }
 }
 
-pub fn hash_table_interface(filename: &str) {
+#[derive(Subcommand)]
+pub enum HashCommand {
+    /// Print the record stored at a date (dd/mm/yyyy)
+    Search {
+        #[arg(short, long)]
+        date: TradeDate,
+    },
+    /// Set the value of the record at a date, then print or save the table
+    Edit {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[arg(short, long)]
+        value: u64,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete the record at a date, then print or save the table
+    Delete {
+        #[arg(short, long)]
+        date: TradeDate,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print or save every record in the table
+    Print {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+pub fn run_hash_command(data: Vec<Data>, command: HashCommand) -> Result<(), String> {
+    let mut vec = build_table(data);
+    let output = match command {
+        HashCommand::Search { date } => {
+            let node = search(&vec, date).ok_or("No data found")?;
+            print_data(&node.data);
+            return Ok(());
+        }
+        HashCommand::Edit { date, value, output } => {
+            let mut data = search(&vec, date).ok_or("No data found")?.data;
+            data.value = value;
+            edit(&mut vec, date, data);
+            output
+        }
+        HashCommand::Delete { date, output } => {
+            if search(&vec, date).is_none() {
+                return Err("No data found".to_string());
+            }
+            delete(&mut vec, date);
+            output
+        }
+        HashCommand::Print { output } => output,
+    };
+
+    output.write(&records(&vec)).map_err(|e| e.to_string())
+}
+
+pub fn hash_table_interface(input: &InputArgs, default_path: &str) {
     let start = SystemTime::now();
-    let mut vec = read_data(filename);
+    let mut vec = match input.load(default_path) {
+        Ok(records) => build_table(records),
+        Err(e) => {
+            println!("Error reading file: {}", e);
+            return;
+        }
+    };
     println!("Time elapsed: {:?}", start.elapsed().unwrap());
 
     loop {
@@ -231,17 +247,18 @@ pub fn hash_table_interface(filename: &str) {
         println!("3. Delete");
         println!("4. Print All");
         println!("0. Back");
-        print!("Enter your choice: ");
-        std::io::stdout().flush().unwrap();
-
-        let choice = user_input();
+        let choice = prompt("Enter your choice: ");
 
         match choice.as_str() {
             "1" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                let node = match search(&vec, &date) {
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let node = match search(&vec, date) {
                     Some(node) => node,
                     None => {
                         println!("No data found");
@@ -251,28 +268,34 @@ pub fn hash_table_interface(filename: &str) {
                 print_data(&node.data);
             }
             "2" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                let node = match search(&vec, &date) {
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let node = match search(&vec, date) {
                     Some(node) => node,
                     None => {
                         println!("No data found");
                         continue;
                     }
                 };
-                println!("Enter new value: ");
-                std::io::stdout().flush().unwrap();
-                let value = user_input();
+                let value = prompt("Enter new value: ");
                 let mut data = node.data;
                 data.value = value.parse::<u64>().unwrap();
-                edit(&mut vec, &date, data);
+                edit(&mut vec, date, data);
             }
             "3" => {
-                print!("Enter date: ");
-                std::io::stdout().flush().unwrap();
-                let date = user_input();
-                delete(&mut vec, &date);
+                let date = match TradeDate::parse(&prompt("Enter date: ")) {
+                    Ok(date) => date,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                delete(&mut vec, date);
             }
             "4" => {
                 print_vec(&vec);
@@ -1,35 +1,67 @@
-#[allow(dead_code)]
 mod avl_tree;
 mod hash_table;
+use std::process;
+
+use clap::{Parser, Subcommand};
 use crate::avl_tree::*;
 use crate::hash_table::*;
-use std::io::Write;
+use trade_core::cli::InputArgs;
+use trade_core::prompt;
 
 const FILE: &str = "effects.csv";
 
-fn user_input() -> String {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    input = input.trim().to_string();
-    input
+#[derive(Parser)]
+#[command(about = "AVL tree and hash table views over the trade records")]
+struct Cli {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Runs the interactive menu when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Query or modify the date-keyed AVL tree
+    #[command(subcommand)]
+    Tree(TreeCommand),
+    /// Query or modify the date-keyed hash table
+    #[command(subcommand)]
+    Hash(HashCommand),
 }
+
 fn main() {
+    let cli = Cli::parse();
+
+    if let Some(command) = cli.command {
+        let result = match cli.input.load(FILE) {
+            Ok(data) => match command {
+                Command::Tree(command) => run_tree_command(&data, command),
+                Command::Hash(command) => run_hash_command(data, command),
+            },
+            Err(e) => Err(format!("Error reading file: {}", e)),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     loop {
         println!("---------------------------");
         println!("1. AVL Tree");
         println!("2. Hash Table");
         println!("0. Exit");
-        print!("Enter your choice: ");
-        std::io::stdout().flush().unwrap();
-
-        let choice = user_input();
+        let choice = prompt("Enter your choice: ");
 
         match choice.as_str() {
             "1" => {
-                avl_tree_interface(FILE);
+                avl_tree_interface(&cli.input, FILE);
             }
             "2" => {
-                hash_table_interface(FILE);
+                hash_table_interface(&cli.input, FILE);
             }
             "0" => {
                 break;
@@ -40,4 +72,4 @@ fn main() {
         }
         println!("bye bye");
     }
-}
\ No newline at end of file
+}
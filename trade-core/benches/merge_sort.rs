@@ -0,0 +1,115 @@
+// Merge sort over synthetic trade records: the index-permutation merge sort in
+// `trade_core::sort` against the previous implementation, which allocated a
+// `Data::new()` placeholder per record and swapped every element back after
+// each merge level. Besides the criterion timings, the bytes each variant
+// allocates per sort are printed, counted by `benchmark::CountingAllocator`.
+//
+//     cargo bench -p trade-core --bench merge_sort
+
+use std::cmp::Ordering;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use trade_core::benchmark::CountingAllocator;
+use trade_core::sort::merge_sort_by;
+use trade_core::Data;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+
+fn records(n: usize) -> Vec<Data> {
+    (0..n as u64)
+        .map(|i| {
+            let mixed = i.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            Data {
+                direction: "Exports".to_string(),
+                year: 2015 + (mixed % 7) as u16,
+                date: format!("{:02}/{:02}/{}", 1 + mixed % 28, 1 + (mixed >> 8) % 12, 2015 + mixed % 7),
+                country: ["China", "Australia", "All"][(mixed % 3) as usize].to_string(),
+                value: mixed % 1_000_000_000,
+                cumulative: i,
+                ..Data::new()
+            }
+        })
+        .collect()
+}
+
+fn by_value(a: &Data, b: &Data) -> Ordering {
+    a.value.cmp(&b.value)
+}
+
+// The merge sort as it was before the index permutation.
+fn placeholder_merge_sort(data: &mut [Data]) {
+    let mut buffer = vec![Data::new(); data.len()];
+    placeholder_merge_sort_par(data, &mut buffer);
+}
+
+fn placeholder_merge_sort_par(data: &mut [Data], buffer: &mut [Data]) {
+    let len = data.len();
+    if len <= 1 {
+        return;
+    }
+
+    let mid = len / 2;
+    let (left, right) = data.split_at_mut(mid);
+    let (left_buf, right_buf) = buffer.split_at_mut(mid);
+    rayon::join(
+        || placeholder_merge_sort_par(left, left_buf),
+        || placeholder_merge_sort_par(right, right_buf),
+    );
+
+    let (mut left_idx, mut right_idx) = (0, 0);
+    for slot in buffer.iter_mut() {
+        let take_from_left = if left_idx < left.len() && right_idx < right.len() {
+            by_value(&left[left_idx], &right[right_idx]) != Ordering::Greater
+        } else {
+            left_idx < left.len()
+        };
+        if take_from_left {
+            std::mem::swap(slot, &mut left[left_idx]);
+            left_idx += 1;
+        } else {
+            std::mem::swap(slot, &mut right[right_idx]);
+            right_idx += 1;
+        }
+    }
+    for (d, b) in data.iter_mut().zip(buffer.iter_mut()) {
+        std::mem::swap(d, b);
+    }
+}
+
+fn allocated_by(sort: impl FnOnce()) -> usize {
+    let before = CountingAllocator::allocated();
+    sort();
+    CountingAllocator::allocated() - before
+}
+
+fn bench_merge_sort(c: &mut Criterion) {
+    for n in SIZES {
+        let mut data = records(n);
+        let placeholder = allocated_by(|| placeholder_merge_sort(&mut data));
+        let mut data = records(n);
+        let permutation = allocated_by(|| merge_sort_by(&mut data, by_value));
+        println!(
+            "merge sort of {} records allocates {} bytes (placeholder buffer: {} bytes)",
+            n, permutation, placeholder
+        );
+    }
+
+    let mut group = c.benchmark_group("merge_sort");
+    group.sample_size(10);
+    for n in SIZES {
+        let input = records(n);
+        group.bench_with_input(BenchmarkId::new("index_permutation", n), &input, |b, input| {
+            b.iter_batched(|| input.clone(), |mut data| merge_sort_by(&mut data, by_value), BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("placeholder_buffer", n), &input, |b, input| {
+            b.iter_batched(|| input.clone(), |mut data| placeholder_merge_sort(&mut data), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_merge_sort);
+criterion_main!(benches);
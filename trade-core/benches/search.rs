@@ -0,0 +1,47 @@
+// Every point search in `trade_core::search` over the query workloads of
+// `trade_core::benchmark`, on synthetic records sorted by date: twenty
+// records a day over six years. Each iteration answers the whole batch of
+// queries, so the criterion figures are per batch of `QUERIES`.
+//
+//     cargo bench -p trade-core --bench search
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use trade_core::benchmark::Workload;
+use trade_core::search::{search_date, SearchStats, SEARCHES};
+use trade_core::{Data, TradeDate};
+
+const QUERIES: usize = 1_000;
+
+fn records() -> Vec<Data> {
+    let first = TradeDate::parse("01/01/2015").unwrap().days();
+    (first..first + 6 * 365)
+        .flat_map(|day| {
+            let date = TradeDate::from_days(day).to_string();
+            (0..20).map(move |i| Data { date: date.clone(), value: i, ..Data::new() })
+        })
+        .collect()
+}
+
+fn bench_search(c: &mut Criterion) {
+    let data = records();
+    for workload in Workload::ALL {
+        let queries = workload.queries(&data, QUERIES, 1);
+        let mut group = c.benchmark_group(format!("search/{}", workload));
+        group.throughput(Throughput::Elements(QUERIES as u64));
+        for search in SEARCHES {
+            group.bench_with_input(BenchmarkId::from_parameter(search.name()), &queries, |b, queries| {
+                b.iter(|| {
+                    for &date in queries {
+                        let _ = black_box(search_date(search, &data, date, &mut SearchStats::default()));
+                    }
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
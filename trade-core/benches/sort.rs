@@ -0,0 +1,43 @@
+// Counting, merge, heap and quick sort by value over the generated inputs of
+// `trade_core::benchmark`, at two sizes, on one thread and on every thread.
+// `parta-ask1 bench` reports comparisons and peak memory as well.
+//
+//     cargo bench -p trade-core --bench sort
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rayon::ThreadPoolBuilder;
+use trade_core::benchmark::{SortAlgorithm, SortInput};
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+
+fn bench_sort(c: &mut Criterion) {
+    // One thread and all of them, once each on a single-core machine.
+    let mut threads = vec![1, rayon::current_num_threads()];
+    threads.dedup();
+    let pools: Vec<_> = threads.into_iter().map(|n| (n, ThreadPoolBuilder::new().num_threads(n).build().unwrap())).collect();
+
+    // Real data needs a loaded export, which `parta-ask1 bench` has.
+    for input in SortInput::ALL.into_iter().filter(|&input| input != SortInput::Real) {
+        let mut group = c.benchmark_group(format!("sort/{}", input));
+        group.sample_size(10);
+        for size in SIZES {
+            let records = input.records(&[], size, 1);
+            for (threads, pool) in &pools {
+                for algorithm in SortAlgorithm::ALL {
+                    let id = BenchmarkId::new(format!("{}/{}t", algorithm, threads), size);
+                    group.bench_with_input(id, &records, |b, records| {
+                        b.iter_batched(
+                            || records.clone(),
+                            |mut data| pool.install(|| algorithm.sort(&mut data)),
+                            BatchSize::LargeInput,
+                        )
+                    });
+                }
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_sort);
+criterion_main!(benches);
@@ -0,0 +1,123 @@
+// Sorting by permutation. Each argsort leaves the records where they are and
+// returns the positions in sorted order as a `Vec<u32>` (4 bytes per record
+// instead of moving whole `Data` structs), so several orderings of the same
+// loaded dataset can be kept side by side. `in_order` walks the records in a
+// permutation's order and `apply_permutation` moves them there for good.
+
+use std::cmp::Ordering;
+
+use crate::sort::{
+    apply_destinations, counting_sort_by_key, heap_sort_by, merge_sort_par, quick_sort_by, radix_sort_by_key,
+};
+
+/// Stable parallel merge sort of the positions of `data`.
+pub fn merge_argsort_by<T, F>(data: &[T], compare: F) -> Vec<u32>
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let mut order = identity(data);
+    let mut buffer = vec![0; order.len()];
+    merge_sort_par(&mut order, &mut buffer, &|&a: &u32, &b: &u32| {
+        compare(&data[a as usize], &data[b as usize])
+    });
+    order
+}
+
+pub fn merge_argsort_by_key<T, K, F>(data: &[T], key: F) -> Vec<u32>
+where
+    T: Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    merge_argsort_by(data, |a, b| key(a).cmp(&key(b)))
+}
+
+/// Quick sort of the positions of `data`. Ties are broken by position, so
+/// unlike `quick_sort_by` the result is stable.
+pub fn quick_argsort_by<T, F>(data: &[T], compare: F) -> Vec<u32>
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let mut order = identity(data);
+    quick_sort_by(&mut order, |&a, &b| compare(&data[a as usize], &data[b as usize]).then(a.cmp(&b)));
+    order
+}
+
+pub fn quick_argsort_by_key<T, K, F>(data: &[T], key: F) -> Vec<u32>
+where
+    T: Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    quick_argsort_by(data, |a, b| key(a).cmp(&key(b)))
+}
+
+/// Heap sort of the positions of `data`, ties broken by position (stable).
+pub fn heap_argsort_by<T, F>(data: &[T], compare: F) -> Vec<u32>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut order = identity(data);
+    heap_sort_by(&mut order, |&a, &b| compare(&data[a as usize], &data[b as usize]).then(a.cmp(&b)));
+    order
+}
+
+pub fn heap_argsort_by_key<T, K, F>(data: &[T], key: F) -> Vec<u32>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    heap_argsort_by(data, |a, b| key(a).cmp(&key(b)))
+}
+
+/// Stable counting sort of the positions of `data` by an integer key.
+pub fn counting_argsort_by_key<T, F>(data: &[T], key: F) -> Vec<u32>
+where
+    F: Fn(&T) -> u64,
+{
+    let mut order = identity(data);
+    counting_sort_by_key(&mut order, |&i| key(&data[i as usize]));
+    order
+}
+
+/// Stable LSD radix sort of the positions of `data` by an integer key.
+pub fn radix_argsort_by_key<T, F>(data: &[T], key: F) -> Vec<u32>
+where
+    F: Fn(&T) -> u64,
+{
+    let mut order = identity(data);
+    radix_sort_by_key(&mut order, |&i| key(&data[i as usize]));
+    order
+}
+
+/// The records of `data` in the order given by `order`.
+///
+/// # Panics
+/// If a position in `order` is out of bounds.
+pub fn in_order<'a, T>(data: &'a [T], order: &'a [u32]) -> impl DoubleEndedIterator<Item = &'a T> + ExactSizeIterator {
+    order.iter().map(move |&i| &data[i as usize])
+}
+
+/// Rearranges `data` so that position `p` holds the record that was at
+/// `order[p]`. The records are swapped along the permutation's cycles, never
+/// cloned.
+///
+/// # Panics
+/// If `order` is not a permutation of `0..data.len()`.
+pub fn apply_permutation<T>(data: &mut [T], order: &[u32]) {
+    assert_eq!(order.len(), data.len(), "permutation length does not match the data");
+    let mut destination = vec![usize::MAX; order.len()];
+    for (position, &i) in order.iter().enumerate() {
+        let slot = &mut destination[i as usize];
+        assert!(*slot == usize::MAX, "position {} appears twice in the permutation", i);
+        *slot = position;
+    }
+    apply_destinations(data, destination);
+}
+
+fn identity<T>(data: &[T]) -> Vec<u32> {
+    let len = u32::try_from(data.len()).expect("argsort supports at most u32::MAX records");
+    (0..len).collect()
+}
@@ -0,0 +1,949 @@
+// A generic AVL tree map with a `BTreeMap`-like interface. The PartB tools
+// used to carry one hand-written AVL tree each, hard-coded to `Data` and a
+// single key; they now index their records with instances of this one, e.g.
+// `AvlTree<Option<TradeDate>, Vec<Data>>` for a date multimap.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+
+type Link<K, V, A> = Option<Box<Node<K, V, A>>>;
+
+#[derive(Clone)]
+struct Node<K, V, A> {
+    key: K,
+    value: V,
+    left: Link<K, V, A>,
+    right: Link<K, V, A>,
+    height: i32,
+    // Entries in the subtree rooted here, for the order statistics.
+    size: usize,
+    // The aggregate of this node's value alone, so rebalancing never has to
+    // go back to the value.
+    own: A,
+    // The values of the subtree rooted here, summed up.
+    aggregate: A,
+}
+
+/// A summary of values that can be built for one value and combined, such as
+/// a total or a maximum. An `AvlTree<K, V, A>` keeps the `A` of every
+/// subtree, so `AvlTree::aggregate` sums up any key range in O(log n).
+///
+/// `combine` must be associative; it is always called with the entries of
+/// `self` before those of `other`.
+pub trait Aggregate<V>: Clone {
+    /// Called once whenever a value is inserted or modified; the result is
+    /// kept next to the value.
+    fn from_value(value: &V) -> Self;
+
+    fn combine(&self, other: &Self) -> Self;
+}
+
+/// No aggregate: the default, for plain maps.
+impl<V> Aggregate<V> for () {
+    fn from_value(_: &V) {}
+
+    fn combine(&self, _: &()) {}
+}
+
+/// An ordered map kept balanced as an AVL tree: the heights of the two
+/// subtrees of every node differ by at most one, so lookups, insertions and
+/// removals are O(log n). Every node also counts the entries below it, which
+/// makes it an order-statistic tree: `select`, `rank` and `count_in_range`
+/// are O(log n) too.
+///
+/// With an `Aggregate` type `A` every node also keeps the aggregate of its
+/// subtree's values. Such a tree does not hand out `&mut V`, since changing a
+/// value behind the tree's back would leave the aggregates stale; values
+/// change through `insert` or `modify` instead.
+#[derive(Clone)]
+pub struct AvlTree<K, V, A = ()> {
+    root: Link<K, V, A>,
+    len: usize,
+}
+
+impl<K, V> AvlTree<K, V> {
+    pub fn new() -> AvlTree<K, V> {
+        AvlTree { root: None, len: 0 }
+    }
+
+    /// The entries in ascending key order, with mutable values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { inner: Traversal::new(self.root.as_deref_mut()), remaining: self.len }
+    }
+}
+
+impl<K, V, A> AvlTree<K, V, A> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// Number of levels: 0 when empty, 1 for a single entry, and at most
+    /// about `1.44 * log2(len)` after that.
+    pub fn height(&self) -> usize {
+        height(&self.root) as usize
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// The entries in ascending key order.
+    pub fn iter(&self) -> Iter<'_, K, V, A> {
+        Iter { inner: Traversal::new(self.root.as_deref()), remaining: self.len }
+    }
+}
+
+impl<K: Ord, V, A: Aggregate<V>> AvlTree<K, V, A> {
+    /// Inserts `value` under `key`, returning the value it replaces, if any.
+    /// The stored key is not updated when it was already present.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = insert(&mut self.root, key, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            match key.cmp(node.key.borrow()) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => link = &node.right,
+                Ordering::Equal => return Some((&node.key, &node.value)),
+            }
+        }
+        None
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key`, returning the stored key and value if it was present.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = remove(&mut self.root, key)?;
+        self.len -= 1;
+        Some((removed.key, removed.value))
+    }
+
+    /// The entries whose keys fall in `range`, in ascending key order, e.g.
+    /// `tree.range(from..=to)`. Finding where the range starts and ends takes
+    /// O(log n); the entries are then visited without touching the rest of
+    /// the tree.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V, A>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let bounds = (range.start_bound(), range.end_bound());
+        let mut inner = Traversal { work: VecDeque::new() };
+        push_range(self.root.as_deref(), bounds, &mut inner.work);
+        Range { inner, remaining: self.count_in_range(bounds) }
+    }
+
+    /// The entry with the largest key less than or equal to `key`.
+    pub fn floor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.closest(key, Ordering::Less, true)
+    }
+
+    /// The entry with the smallest key greater than or equal to `key`.
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.closest(key, Ordering::Greater, true)
+    }
+
+    /// The entry with the largest key strictly less than `key`, which need
+    /// not be in the tree.
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.closest(key, Ordering::Less, false)
+    }
+
+    /// The entry with the smallest key strictly greater than `key`.
+    pub fn successor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.closest(key, Ordering::Greater, false)
+    }
+
+    // The nearest entry on the `side` of `key` (`Less` or `Greater`), or the
+    // entry for `key` itself when `inclusive`.
+    fn closest<Q>(&self, key: &Q, side: Ordering, inclusive: bool) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        let mut best = None;
+        while let Some(node) = link {
+            let order = node.key.borrow().cmp(key);
+            if order == Ordering::Equal && inclusive {
+                return Some((&node.key, &node.value));
+            }
+            if order == side {
+                // A candidate; anything closer lies towards `key`.
+                best = Some((&node.key, &node.value));
+                link = if side == Ordering::Less { &node.right } else { &node.left };
+            } else {
+                link = if side == Ordering::Less { &node.left } else { &node.right };
+            }
+        }
+        best
+    }
+
+    /// The entry with `k` smaller keys before it (the smallest for `k == 0`),
+    /// or `None` when `k >= len`.
+    pub fn select(&self, mut k: usize) -> Option<(&K, &V)> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            let left = size(&node.left);
+            match k.cmp(&left) {
+                Ordering::Less => link = &node.left,
+                Ordering::Equal => return Some((&node.key, &node.value)),
+                Ordering::Greater => {
+                    k -= left + 1;
+                    link = &node.right;
+                }
+            }
+        }
+        None
+    }
+
+    /// Number of keys less than `key`, whether or not `key` is present: the
+    /// position `select` finds it at, or where it would be inserted.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.count_below(key, false)
+    }
+
+    /// Number of keys in `range`, e.g. `tree.count_in_range(a..=b)`, without
+    /// visiting them.
+    pub fn count_in_range<Q, R>(&self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let before_start = match range.start_bound() {
+            Bound::Included(start) => self.count_below(start, false),
+            Bound::Excluded(start) => self.count_below(start, true),
+            Bound::Unbounded => 0,
+        };
+        let up_to_end = match range.end_bound() {
+            Bound::Included(end) => self.count_below(end, true),
+            Bound::Excluded(end) => self.count_below(end, false),
+            Bound::Unbounded => self.len,
+        };
+        up_to_end.saturating_sub(before_start)
+    }
+
+    // Number of keys less than `key`, or less than or equal when `inclusive`.
+    fn count_below<Q>(&self, key: &Q, inclusive: bool) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        let mut count = 0;
+        while let Some(node) = link {
+            match node.key.borrow().cmp(key) {
+                Ordering::Less => {
+                    count += size(&node.left) + 1;
+                    link = &node.right;
+                }
+                Ordering::Equal if inclusive => return count + size(&node.left) + 1,
+                Ordering::Equal => return count + size(&node.left),
+                Ordering::Greater => link = &node.left,
+            }
+        }
+        count
+    }
+
+    /// Applies `f` to the value of `key` and refreshes the aggregates above
+    /// it. `None`, without calling `f`, if the key is absent.
+    pub fn modify<Q, F, R>(&mut self, key: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        modify(&mut self.root, key, f)
+    }
+
+    /// The aggregate of the values whose keys fall in `range`, e.g.
+    /// `tree.aggregate(from..=to)`, or `None` for an empty range. Combines
+    /// O(log n) stored subtree aggregates without visiting the entries.
+    pub fn aggregate<Q, R>(&self, range: R) -> Option<A>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        fold_range(self.root.as_deref(), (range.start_bound(), range.end_bound()))
+    }
+}
+
+impl<K: Ord, V> AvlTree<K, V> {
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find_mut(&mut self.root, key).map(|node| &mut node.value)
+    }
+
+    /// The entry for `key`, for in-place updates such as
+    /// `tree.entry(date).or_default().push(record)`. The entry keeps the key,
+    /// not a handle on its node, so each of its lookups walks down from the
+    /// root again: O(log n) per call on top of this one.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        if self.contains_key(&key) {
+            Entry::Occupied(OccupiedEntry { tree: self, key })
+        } else {
+            Entry::Vacant(VacantEntry { tree: self, key })
+        }
+    }
+}
+
+impl<K, V, A> Default for AvlTree<K, V, A> {
+    fn default() -> AvlTree<K, V, A> {
+        AvlTree { root: None, len: 0 }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, A> fmt::Debug for AvlTree<K, V, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V, A: Aggregate<V>> FromIterator<(K, V)> for AvlTree<K, V, A> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> AvlTree<K, V, A> {
+        let mut tree = AvlTree::default();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: Ord, V, A: Aggregate<V>> Extend<(K, V)> for AvlTree<K, V, A> {
+    /// Later values replace earlier ones with the same key, as with `insert`.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, A> IntoIterator for &'a AvlTree<K, V, A> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, A>;
+
+    fn into_iter(self) -> Iter<'a, K, V, A> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut AvlTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, A> IntoIterator for AvlTree<K, V, A> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    /// The entries in ascending key order, moved out of the tree.
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        IntoIter { inner: Traversal::new(self.root), remaining: self.len }
+    }
+}
+
+/// A view into one key of an `AvlTree`, from `AvlTree::entry`.
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+/// An absent key. Filling it clones the key once, to find the new node again
+/// after the insertion has rebalanced the tree, so `insert` costs two
+/// O(log n) walks.
+pub struct VacantEntry<'a, K, V> {
+    tree: &'a mut AvlTree<K, V>,
+    key: K,
+}
+
+/// A present key. `get`, `get_mut`, `into_mut`, `insert` and `remove` each
+/// look the key up again in O(log n): a handle on the node would hold a
+/// borrow of the tree that `remove` needs to rebalance it.
+pub struct OccupiedEntry<'a, K, V> {
+    tree: &'a mut AvlTree<K, V>,
+    key: K,
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Vacant(entry) => &entry.key,
+            Entry::Occupied(entry) => &entry.key,
+        }
+    }
+
+    /// Updates the value in place if the key is present.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Entry<'a, K, V> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            vacant => vacant,
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V> Entry<'a, K, V> {
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K: Ord + Clone, V> VacantEntry<'a, K, V> {
+    pub fn insert(self, value: V) -> &'a mut V {
+        let VacantEntry { tree, key } = self;
+        tree.insert(key.clone(), value);
+        tree.get_mut(&key).expect("the key was just inserted")
+    }
+}
+
+impl<K, V> VacantEntry<'_, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.tree.get(&self.key).expect("an occupied entry's key is present")
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.tree.get_mut(&self.key).expect("an occupied entry's key is present")
+    }
+
+    /// The value, borrowed for as long as the tree was.
+    pub fn into_mut(self) -> &'a mut V {
+        self.tree.get_mut(&self.key).expect("an occupied entry's key is present")
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry from the tree, returning its value.
+    pub fn remove(self) -> V {
+        self.tree.remove(&self.key).expect("an occupied entry's key is present")
+    }
+}
+
+// A node an iterator can take apart into its two subtrees and its entry:
+// a shared or a mutable borrow, or an owned node.
+trait Split: Sized {
+    type Entry;
+
+    fn split(self) -> (Option<Self>, Self::Entry, Option<Self>);
+}
+
+impl<'a, K, V, A> Split for &'a Node<K, V, A> {
+    type Entry = (&'a K, &'a V);
+
+    fn split(self) -> (Option<Self>, Self::Entry, Option<Self>) {
+        (self.left.as_deref(), (&self.key, &self.value), self.right.as_deref())
+    }
+}
+
+impl<'a, K, V, A> Split for &'a mut Node<K, V, A> {
+    type Entry = (&'a K, &'a mut V);
+
+    fn split(self) -> (Option<Self>, Self::Entry, Option<Self>) {
+        let Node { key, value, left, right, .. } = self;
+        (left.as_deref_mut(), (&*key, value), right.as_deref_mut())
+    }
+}
+
+impl<K, V, A> Split for Box<Node<K, V, A>> {
+    type Entry = (K, V);
+
+    fn split(self) -> (Option<Self>, Self::Entry, Option<Self>) {
+        let Node { key, value, left, right, .. } = *self;
+        (left, (key, value), right)
+    }
+}
+
+enum Work<N: Split> {
+    Tree(N),
+    Entry(N::Entry),
+}
+
+// In-order traversal from both ends. `work` holds, in key order, whole
+// subtrees still to visit and entries already split off them; each end
+// splits the subtree nearest to it until an entry surfaces, so both ends
+// stay O(log n) deep and never overlap.
+struct Traversal<N: Split> {
+    work: VecDeque<Work<N>>,
+}
+
+impl<N: Split> Traversal<N> {
+    fn new(root: Option<N>) -> Traversal<N> {
+        Traversal { work: root.map(Work::Tree).into_iter().collect() }
+    }
+
+    fn next(&mut self) -> Option<N::Entry> {
+        loop {
+            match self.work.pop_front()? {
+                Work::Entry(entry) => return Some(entry),
+                Work::Tree(node) => {
+                    let (left, entry, right) = node.split();
+                    if let Some(right) = right {
+                        self.work.push_front(Work::Tree(right));
+                    }
+                    self.work.push_front(Work::Entry(entry));
+                    if let Some(left) = left {
+                        self.work.push_front(Work::Tree(left));
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_back(&mut self) -> Option<N::Entry> {
+        loop {
+            match self.work.pop_back()? {
+                Work::Entry(entry) => return Some(entry),
+                Work::Tree(node) => {
+                    let (left, entry, right) = node.split();
+                    if let Some(left) = left {
+                        self.work.push_back(Work::Tree(left));
+                    }
+                    self.work.push_back(Work::Entry(entry));
+                    if let Some(right) = right {
+                        self.work.push_back(Work::Tree(right));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Queues, in key order, the entries and whole subtrees under `node` that
+// fall within `bounds`. Past the node where the two bounds part ways only
+// one bound is left to check on each side, so this visits O(log n) nodes.
+fn push_range<'a, K, V, A, Q>(node: Option<&'a Node<K, V, A>>, bounds: (Bound<&Q>, Bound<&Q>), work: &mut VecDeque<Work<&'a Node<K, V, A>>>)
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let Some(node) = node else {
+        return;
+    };
+    let (start, end) = bounds;
+    if let (Bound::Unbounded, Bound::Unbounded) = bounds {
+        work.push_back(Work::Tree(node));
+        return;
+    }
+    let (before_start, after_end) = outside(node.key.borrow(), bounds);
+    if before_start {
+        push_range(node.right.as_deref(), bounds, work);
+    } else if after_end {
+        push_range(node.left.as_deref(), bounds, work);
+    } else {
+        push_range(node.left.as_deref(), (start, Bound::Unbounded), work);
+        work.push_back(Work::Entry((&node.key, &node.value)));
+        push_range(node.right.as_deref(), (Bound::Unbounded, end), work);
+    }
+}
+
+// The aggregate of the values under `node` within `bounds`, split up the
+// same way as `push_range`.
+fn fold_range<K, V, A, Q>(node: Option<&Node<K, V, A>>, bounds: (Bound<&Q>, Bound<&Q>)) -> Option<A>
+where
+    K: Borrow<Q>,
+    A: Aggregate<V>,
+    Q: Ord + ?Sized,
+{
+    let node = node?;
+    let (start, end) = bounds;
+    if let (Bound::Unbounded, Bound::Unbounded) = bounds {
+        return Some(node.aggregate.clone());
+    }
+    let (before_start, after_end) = outside(node.key.borrow(), bounds);
+    if before_start {
+        fold_range(node.right.as_deref(), bounds)
+    } else if after_end {
+        fold_range(node.left.as_deref(), bounds)
+    } else {
+        let own = node.own.clone();
+        let left = fold_range(node.left.as_deref(), (start, Bound::Unbounded));
+        let right = fold_range(node.right.as_deref(), (Bound::Unbounded, end));
+        let aggregate = match left {
+            Some(left) => left.combine(&own),
+            None => own,
+        };
+        Some(match right {
+            Some(right) => aggregate.combine(&right),
+            None => aggregate,
+        })
+    }
+}
+
+// Whether `key` lies before the start or after the end of `bounds`.
+fn outside<Q: Ord + ?Sized>(key: &Q, bounds: (Bound<&Q>, Bound<&Q>)) -> (bool, bool) {
+    let before_start = match bounds.0 {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    };
+    let after_end = match bounds.1 {
+        Bound::Included(end) => key > end,
+        Bound::Excluded(end) => key >= end,
+        Bound::Unbounded => false,
+    };
+    (before_start, after_end)
+}
+
+/// Borrowing iterator over the entries of an `AvlTree` in ascending key
+/// order, from `AvlTree::iter`.
+pub struct Iter<'a, K, V, A = ()> {
+    inner: Traversal<&'a Node<K, V, A>>,
+    remaining: usize,
+}
+
+/// Iterator over the entries of an `AvlTree` with mutable values, from
+/// `AvlTree::iter_mut`.
+pub struct IterMut<'a, K, V, A = ()> {
+    inner: Traversal<&'a mut Node<K, V, A>>,
+    remaining: usize,
+}
+
+/// Owning iterator over the entries of an `AvlTree`, from `into_iter`.
+pub struct IntoIter<K, V, A = ()> {
+    inner: Traversal<Box<Node<K, V, A>>>,
+    remaining: usize,
+}
+
+/// Iterator over the entries of an `AvlTree` within a key range, from
+/// `AvlTree::range`.
+pub struct Range<'a, K, V, A = ()> {
+    inner: Traversal<&'a Node<K, V, A>>,
+    remaining: usize,
+}
+
+// The iterators differ only in how they hold the nodes.
+macro_rules! impl_iterator {
+    ($iter:ty, $item:ty) => {
+        impl<'a, K, V, A> Iterator for $iter {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<$item> {
+                let entry = self.inner.next()?;
+                self.remaining -= 1;
+                Some(entry)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.remaining, Some(self.remaining))
+            }
+        }
+
+        impl<'a, K, V, A> DoubleEndedIterator for $iter {
+            fn next_back(&mut self) -> Option<$item> {
+                let entry = self.inner.next_back()?;
+                self.remaining -= 1;
+                Some(entry)
+            }
+        }
+
+        impl<'a, K, V, A> ExactSizeIterator for $iter {}
+
+        impl<'a, K, V, A> FusedIterator for $iter {}
+    };
+}
+
+impl_iterator!(Iter<'a, K, V, A>, (&'a K, &'a V));
+impl_iterator!(IterMut<'a, K, V, A>, (&'a K, &'a mut V));
+impl_iterator!(IntoIter<K, V, A>, (K, V));
+impl_iterator!(Range<'a, K, V, A>, (&'a K, &'a V));
+
+fn height<K, V, A>(link: &Link<K, V, A>) -> i32 {
+    match link {
+        Some(node) => node.height,
+        None => 0,
+    }
+}
+
+fn balance_factor<K, V, A>(node: &Node<K, V, A>) -> i32 {
+    height(&node.left) - height(&node.right)
+}
+
+fn size<K, V, A>(link: &Link<K, V, A>) -> usize {
+    match link {
+        Some(node) => node.size,
+        None => 0,
+    }
+}
+
+// Recomputes what a node keeps about its subtree from its children.
+fn update<K, V, A: Aggregate<V>>(node: &mut Node<K, V, A>) {
+    node.height = height(&node.left).max(height(&node.right)) + 1;
+    node.size = size(&node.left) + size(&node.right) + 1;
+    let mut aggregate = node.own.clone();
+    if let Some(left) = &node.left {
+        aggregate = left.aggregate.combine(&aggregate);
+    }
+    if let Some(right) = &node.right {
+        aggregate = aggregate.combine(&right.aggregate);
+    }
+    node.aggregate = aggregate;
+}
+
+fn rotate_left<K, V, A: Aggregate<V>>(mut node: Box<Node<K, V, A>>) -> Box<Node<K, V, A>> {
+    let mut new_root = node.right.take().unwrap();
+    node.right = new_root.left.take();
+    update(&mut node);
+    new_root.left = Some(node);
+    update(&mut new_root);
+    new_root
+}
+
+fn rotate_right<K, V, A: Aggregate<V>>(mut node: Box<Node<K, V, A>>) -> Box<Node<K, V, A>> {
+    let mut new_root = node.left.take().unwrap();
+    node.left = new_root.right.take();
+    update(&mut node);
+    new_root.right = Some(node);
+    update(&mut new_root);
+    new_root
+}
+
+fn balance<K, V, A: Aggregate<V>>(mut node: Box<Node<K, V, A>>) -> Box<Node<K, V, A>> {
+    update(&mut node);
+    if balance_factor(&node) > 1 {
+        if balance_factor(node.left.as_ref().unwrap()) < 0 {
+            node.left = Some(rotate_left(node.left.take().unwrap()));
+        }
+        return rotate_right(node);
+    }
+    if balance_factor(&node) < -1 {
+        if balance_factor(node.right.as_ref().unwrap()) > 0 {
+            node.right = Some(rotate_right(node.right.take().unwrap()));
+        }
+        return rotate_left(node);
+    }
+    node
+}
+
+// Restores the height, counts and balance of the node at `link` after one
+// of its subtrees changed.
+fn rebalance<K, V, A: Aggregate<V>>(link: &mut Link<K, V, A>) {
+    if let Some(node) = link.take() {
+        *link = Some(balance(node));
+    }
+}
+
+fn insert<K: Ord, V, A: Aggregate<V>>(link: &mut Link<K, V, A>, key: K, value: V) -> Option<V> {
+    let Some(node) = link else {
+        let own = A::from_value(&value);
+        let aggregate = own.clone();
+        *link = Some(Box::new(Node { key, value, left: None, right: None, height: 1, size: 1, own, aggregate }));
+        return None;
+    };
+    let old = match key.cmp(&node.key) {
+        Ordering::Equal => {
+            let old = mem::replace(&mut node.value, value);
+            node.own = A::from_value(&node.value);
+            update(node);
+            return Some(old);
+        }
+        Ordering::Less => insert(&mut node.left, key, value),
+        Ordering::Greater => insert(&mut node.right, key, value),
+    };
+    rebalance(link);
+    old
+}
+
+fn find_mut<'a, K, V, Q>(link: &'a mut Link<K, V, ()>, key: &Q) -> Option<&'a mut Node<K, V, ()>>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let node = link.as_deref_mut()?;
+    match key.cmp(node.key.borrow()) {
+        Ordering::Less => find_mut(&mut node.left, key),
+        Ordering::Greater => find_mut(&mut node.right, key),
+        Ordering::Equal => Some(node),
+    }
+}
+
+// Applies `f` to the value of `key`, then updates the nodes on the way back
+// up to the root.
+fn modify<K, V, A, Q, F, R>(link: &mut Link<K, V, A>, key: &Q, f: F) -> Option<R>
+where
+    K: Borrow<Q>,
+    A: Aggregate<V>,
+    Q: Ord + ?Sized,
+    F: FnOnce(&mut V) -> R,
+{
+    let node = link.as_deref_mut()?;
+    let result = match key.cmp(node.key.borrow()) {
+        Ordering::Less => modify(&mut node.left, key, f),
+        Ordering::Greater => modify(&mut node.right, key, f),
+        Ordering::Equal => {
+            let result = f(&mut node.value);
+            node.own = A::from_value(&node.value);
+            Some(result)
+        }
+    }?;
+    update(node);
+    Some(result)
+}
+
+// Unlinks the node holding `key` and returns it, detached from its children.
+fn remove<K, V, A, Q>(link: &mut Link<K, V, A>, key: &Q) -> Option<Box<Node<K, V, A>>>
+where
+    K: Borrow<Q>,
+    A: Aggregate<V>,
+    Q: Ord + ?Sized,
+{
+    let node = link.as_mut()?;
+    let removed = match key.cmp(node.key.borrow()) {
+        Ordering::Less => remove(&mut node.left, key),
+        Ordering::Greater => remove(&mut node.right, key),
+        Ordering::Equal => {
+            let mut removed = link.take().unwrap();
+            *link = match (removed.left.take(), removed.right.take()) {
+                (None, child) | (child, None) => child,
+                // Two children: the in-order successor takes the node's place.
+                (left, mut right) => {
+                    let mut successor = remove_min(&mut right).unwrap();
+                    successor.left = left;
+                    successor.right = right;
+                    Some(successor)
+                }
+            };
+            Some(removed)
+        }
+    };
+    rebalance(link);
+    removed
+}
+
+// Unlinks the node with the smallest key under `link`.
+fn remove_min<K, V, A: Aggregate<V>>(link: &mut Link<K, V, A>) -> Option<Box<Node<K, V, A>>> {
+    let node = link.as_mut()?;
+    if node.left.is_some() {
+        let min = remove_min(&mut node.left);
+        rebalance(link);
+        return min;
+    }
+    let mut min = link.take().unwrap();
+    *link = min.right.take();
+    Some(min)
+}
@@ -0,0 +1,664 @@
+// Benchmark harness for the tools' `bench` commands. The data is loaded once;
+// each search then answers the same generated queries, and every query is
+// timed over a batch of repeats so the figures are not timer noise. Sorts run
+// over generated inputs at several sizes and thread counts. Reports are
+// written as an aligned table, CSV or JSON.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hint::black_box;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::search::{search_date, Search, SearchStats};
+use crate::select::{median, percentile};
+use crate::sort::{counting_sort_by_key, heap_sort_by, merge_sort_by, par_heap_sort_by, quick_sort_by};
+use crate::{Data, TradeDate};
+
+/// Which dates a search benchmark asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Any day between the first and last date in the data.
+    Uniform,
+    /// Dates of records picked at random, so every query hits.
+    FromData,
+    /// Days with no records, inside and around the data's range.
+    Misses,
+    /// Dates of records picked mostly from the start of the data.
+    Skewed,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 4] = [Workload::Uniform, Workload::FromData, Workload::Misses, Workload::Skewed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Workload::Uniform => "uniform",
+            Workload::FromData => "from-data",
+            Workload::Misses => "misses",
+            Workload::Skewed => "skewed",
+        }
+    }
+
+    /// `count` query dates for records sorted by date. Empty if no record
+    /// has a valid date.
+    pub fn queries(&self, data: &[Data], count: usize, seed: u64) -> Vec<TradeDate> {
+        let dates: Vec<TradeDate> = data.iter().filter_map(|d| d.trade_date().ok()).collect();
+        let (Some(first), Some(last)) = (dates.first(), dates.last()) else {
+            return Vec::new();
+        };
+        let (first, last) = (first.days(), last.days());
+        let mut rng = Rng::new(seed);
+
+        match self {
+            Workload::Uniform => (0..count).map(|_| TradeDate::from_days(first + rng.below((last - first + 1) as u64) as i32)).collect(),
+            Workload::FromData => (0..count).map(|_| dates[rng.below(dates.len() as u64) as usize]).collect(),
+            Workload::Skewed => (0..count)
+                .map(|_| {
+                    // Cubing a uniform fraction puts half the queries in the
+                    // first eighth of the data.
+                    let fraction = rng.fraction().powi(3);
+                    dates[(fraction * dates.len() as f64) as usize]
+                })
+                .collect(),
+            Workload::Misses => {
+                // Up to a year either side of the data, so some misses fall
+                // outside the range and some inside it.
+                let (low, span) = (first - 365, (last - first + 731) as u64);
+                let mut queries = Vec::with_capacity(count);
+                while queries.len() < count {
+                    let date = TradeDate::from_days(low + rng.below(span) as i32);
+                    if dates.binary_search(&date).is_err() {
+                        queries.push(date);
+                    }
+                }
+                queries
+            }
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Workload, String> {
+        Workload::ALL
+            .into_iter()
+            .find(|workload| workload.name() == s)
+            .ok_or_else(|| format!("unknown workload '{}', expected uniform, from-data, misses or skewed", s))
+    }
+}
+
+/// How a benchmark report is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "table" => Ok(ReportFormat::Table),
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!("unknown report format '{}', expected table, csv or json", s)),
+        }
+    }
+}
+
+/// Latency and cost of one search over one workload. Latencies are per
+/// query; probes and jumps are averaged over the queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub search: &'static str,
+    pub workload: Workload,
+    pub queries: usize,
+    pub hits: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p99: Duration,
+    pub mean_probes: f64,
+    pub max_probes: usize,
+    pub mean_jumps: f64,
+}
+
+/// Runs searches over query workloads on records sorted by date.
+#[derive(Debug, Clone)]
+pub struct SearchBenchmark {
+    queries: usize,
+    repeats: usize,
+    seed: u64,
+}
+
+impl Default for SearchBenchmark {
+    fn default() -> SearchBenchmark {
+        SearchBenchmark { queries: 10_000, repeats: 16, seed: 0x5EED }
+    }
+}
+
+impl SearchBenchmark {
+    pub fn new() -> SearchBenchmark {
+        SearchBenchmark::default()
+    }
+
+    /// Queries generated per workload. Defaults to 10 000.
+    pub fn queries(mut self, queries: usize) -> SearchBenchmark {
+        self.queries = queries;
+        self
+    }
+
+    /// Times each query is run back to back within one measurement; the
+    /// latency is the batch time divided by this. Defaults to 16, minimum 1.
+    pub fn repeats(mut self, repeats: usize) -> SearchBenchmark {
+        self.repeats = repeats.max(1);
+        self
+    }
+
+    /// Seed for the query generator, so runs can be compared.
+    pub fn seed(mut self, seed: u64) -> SearchBenchmark {
+        self.seed = seed;
+        self
+    }
+
+    /// One result per workload and search, workload by workload. Every
+    /// search answers the same queries.
+    pub fn run(&self, data: &[Data], searches: &[&dyn Search], workloads: &[Workload]) -> Vec<SearchResult> {
+        let mut results = Vec::with_capacity(searches.len() * workloads.len());
+        for &workload in workloads {
+            let queries = workload.queries(data, self.queries, self.seed);
+            for &search in searches {
+                results.push(self.measure(data, search, workload, &queries));
+            }
+        }
+        results
+    }
+
+    fn measure(&self, data: &[Data], search: &dyn Search, workload: Workload, queries: &[TradeDate]) -> SearchResult {
+        let mut nanos = Vec::with_capacity(queries.len());
+        let (mut total, mut max_probes, mut hits) = (SearchStats::default(), 0, 0);
+        for &date in queries {
+            let mut stats = SearchStats::default();
+            hits += search_date(search, data, date, &mut stats).is_ok() as usize;
+            max_probes = max_probes.max(stats.probes);
+            total.probes += stats.probes;
+            total.jumps += stats.jumps;
+
+            let start = Instant::now();
+            for _ in 0..self.repeats {
+                let _ = black_box(search_date(search, black_box(data), black_box(date), &mut SearchStats::default()));
+            }
+            nanos.push(start.elapsed().as_nanos() / self.repeats as u128);
+        }
+
+        let per_query = |total: usize| total as f64 / queries.len().max(1) as f64;
+        let nanos_at = |value: Option<u128>| Duration::from_nanos(value.unwrap_or(0) as u64);
+        SearchResult {
+            search: search.name(),
+            workload,
+            queries: queries.len(),
+            hits,
+            mean: nanos_at(nanos.iter().sum::<u128>().checked_div(nanos.len() as u128)),
+            median: nanos_at(median(&nanos, |&n| n)),
+            p99: nanos_at(percentile(&nanos, 99.0, |&n| n)),
+            mean_probes: per_query(total.probes),
+            max_probes,
+            mean_jumps: per_query(total.jumps),
+        }
+    }
+}
+
+/// A sort the sort benchmark runs. Every one orders the records by value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortAlgorithm {
+    Counting,
+    Merge,
+    Heap,
+    Quick,
+}
+
+impl SortAlgorithm {
+    pub const ALL: [SortAlgorithm; 4] = [SortAlgorithm::Counting, SortAlgorithm::Merge, SortAlgorithm::Heap, SortAlgorithm::Quick];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortAlgorithm::Counting => "counting",
+            SortAlgorithm::Merge => "merge",
+            SortAlgorithm::Heap => "heap",
+            SortAlgorithm::Quick => "quick",
+        }
+    }
+
+    /// Sorts the records by value on the current rayon pool. Heap sort uses
+    /// its parallel variant when the pool has more than one thread.
+    pub fn sort(&self, data: &mut [Data]) {
+        self.sort_with(data, |a, b| a.value.cmp(&b.value));
+    }
+
+    // `compare` must order by value; counting sort reads the value directly
+    // and never calls it.
+    fn sort_with<F>(&self, data: &mut [Data], compare: F)
+    where
+        F: Fn(&Data, &Data) -> Ordering + Sync,
+    {
+        match self {
+            SortAlgorithm::Counting => counting_sort_by_key(data, |d| d.value),
+            SortAlgorithm::Merge => merge_sort_by(data, compare),
+            SortAlgorithm::Heap if rayon::current_num_threads() == 1 => heap_sort_by(data, compare),
+            SortAlgorithm::Heap => par_heap_sort_by(data, compare),
+            SortAlgorithm::Quick => quick_sort_by(data, compare),
+        }
+    }
+}
+
+impl fmt::Display for SortAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SortAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortAlgorithm, String> {
+        SortAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or_else(|| format!("unknown sort '{}', expected counting, merge, heap or quick", s))
+    }
+}
+
+/// The records a sort benchmark sorts, by how their values are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortInput {
+    /// Values spread at random up to a billion.
+    Random,
+    /// Already in ascending order.
+    Sorted,
+    /// In descending order.
+    Reversed,
+    /// Only 16 distinct values.
+    Duplicates,
+    /// Records drawn at random from the loaded data.
+    Real,
+}
+
+impl SortInput {
+    pub const ALL: [SortInput; 5] = [SortInput::Random, SortInput::Sorted, SortInput::Reversed, SortInput::Duplicates, SortInput::Real];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortInput::Random => "random",
+            SortInput::Sorted => "sorted",
+            SortInput::Reversed => "reversed",
+            SortInput::Duplicates => "duplicates",
+            SortInput::Real => "real",
+        }
+    }
+
+    /// `size` records. `cumulative` holds each record's input position, so
+    /// the sorted output shows where every record came from. `Real` takes its
+    /// records from `data` and is empty when `data` is.
+    pub fn records(&self, data: &[Data], size: usize, seed: u64) -> Vec<Data> {
+        let mut rng = Rng::new(seed);
+        let synthetic = |value: u64| Data { value, ..Data::new() };
+        let mut records: Vec<Data> = match self {
+            SortInput::Random => (0..size).map(|_| synthetic(rng.below(1_000_000_000))).collect(),
+            SortInput::Sorted => (0..size as u64).map(|i| synthetic(i * 10)).collect(),
+            SortInput::Reversed => (0..size as u64).rev().map(|i| synthetic(i * 10)).collect(),
+            SortInput::Duplicates => (0..size).map(|_| synthetic(rng.below(16) * 1_000)).collect(),
+            SortInput::Real if data.is_empty() => Vec::new(),
+            SortInput::Real => (0..size).map(|_| data[rng.below(data.len() as u64) as usize].clone()).collect(),
+        };
+        for (i, d) in records.iter_mut().enumerate() {
+            d.cumulative = i as u64;
+        }
+        records
+    }
+}
+
+impl fmt::Display for SortInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SortInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SortInput, String> {
+        SortInput::ALL
+            .into_iter()
+            .find(|input| input.name() == s)
+            .ok_or_else(|| format!("unknown sort input '{}', expected random, sorted, reversed, duplicates or real", s))
+    }
+}
+
+/// Time and cost of one sort of one input. `peak_memory` is `None` unless
+/// `CountingAllocator` is the global allocator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortResult {
+    pub algorithm: SortAlgorithm,
+    pub input: SortInput,
+    pub size: usize,
+    pub threads: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub comparisons: u64,
+    pub peak_memory: Option<usize>,
+}
+
+/// Runs sorts over generated inputs at several sizes and thread counts.
+#[derive(Debug, Clone)]
+pub struct SortBenchmark {
+    sizes: Vec<usize>,
+    threads: Vec<usize>,
+    repeats: usize,
+    seed: u64,
+}
+
+impl Default for SortBenchmark {
+    fn default() -> SortBenchmark {
+        SortBenchmark { sizes: vec![1_000, 10_000, 100_000], threads: vec![1, rayon::current_num_threads()], repeats: 3, seed: 0x5EED }
+    }
+}
+
+impl SortBenchmark {
+    pub fn new() -> SortBenchmark {
+        SortBenchmark::default()
+    }
+
+    /// Record counts to sort. Defaults to 1 000, 10 000 and 100 000.
+    pub fn sizes(mut self, sizes: Vec<usize>) -> SortBenchmark {
+        self.sizes = sizes;
+        self
+    }
+
+    /// Thread pool sizes to sort with. Defaults to one thread and all of
+    /// them; repeated sizes are measured once.
+    pub fn threads(mut self, threads: Vec<usize>) -> SortBenchmark {
+        self.threads = threads;
+        self
+    }
+
+    /// Timed sorts per measurement. Defaults to 3, minimum 1.
+    pub fn repeats(mut self, repeats: usize) -> SortBenchmark {
+        self.repeats = repeats.max(1);
+        self
+    }
+
+    /// Seed for the generated inputs, so runs can be compared.
+    pub fn seed(mut self, seed: u64) -> SortBenchmark {
+        self.seed = seed;
+        self
+    }
+
+    /// One result per input, size, thread count and algorithm, in that
+    /// nesting. Every algorithm sorts the same records. `data` is only used
+    /// for `SortInput::Real`, which is skipped when it is empty.
+    pub fn run(&self, data: &[Data], algorithms: &[SortAlgorithm], inputs: &[SortInput]) -> Result<Vec<SortResult>, ThreadPoolBuildError> {
+        // On a single core "one thread and all of them" is the same pool twice.
+        let mut threads = self.threads.clone();
+        threads.sort_unstable();
+        threads.dedup();
+        let pools = threads
+            .iter()
+            .map(|&threads| Ok((threads, ThreadPoolBuilder::new().num_threads(threads).build()?)))
+            .collect::<Result<Vec<_>, ThreadPoolBuildError>>()?;
+
+        let mut results = Vec::new();
+        for &input in inputs {
+            for &size in &self.sizes {
+                let records = input.records(data, size, self.seed);
+                if records.len() != size {
+                    continue;
+                }
+                for (threads, pool) in &pools {
+                    for &algorithm in algorithms {
+                        results.push(self.measure(pool, &records, algorithm, input, *threads));
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn measure(&self, pool: &ThreadPool, records: &[Data], algorithm: SortAlgorithm, input: SortInput, threads: usize) -> SortResult {
+        // One counted sort for the comparisons and memory...
+        let comparisons = AtomicU64::new(0);
+        let mut sorted = records.to_vec();
+        CountingAllocator::reset_peak();
+        let in_use = CountingAllocator::in_use();
+        pool.install(|| {
+            algorithm.sort_with(&mut sorted, |a, b| {
+                comparisons.fetch_add(1, AtomicOrdering::Relaxed);
+                a.value.cmp(&b.value)
+            })
+        });
+        let peak_memory = (in_use > 0).then(|| CountingAllocator::peak().saturating_sub(in_use));
+
+        // ...then the timed ones, without the counter.
+        let mut times = Vec::with_capacity(self.repeats);
+        for _ in 0..self.repeats {
+            let mut data = records.to_vec();
+            let start = Instant::now();
+            pool.install(|| algorithm.sort(&mut data));
+            times.push(start.elapsed());
+            black_box(data);
+        }
+
+        SortResult {
+            algorithm,
+            input,
+            size: records.len(),
+            threads,
+            mean: times.iter().sum::<Duration>() / times.len() as u32,
+            min: times.iter().copied().min().unwrap_or_default(),
+            comparisons: comparisons.into_inner(),
+            peak_memory,
+        }
+    }
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts the bytes allocated through it. A binary
+/// that installs it gets peak memory figures from `SortBenchmark`:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    /// Bytes allocated since the program started, freed or not.
+    pub fn allocated() -> usize {
+        ALLOCATED.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Bytes currently allocated. Always 0 if the allocator is not installed.
+    pub fn in_use() -> usize {
+        IN_USE.load(AtomicOrdering::Relaxed)
+    }
+
+    /// The most bytes allocated at once since the last `reset_peak`.
+    pub fn peak() -> usize {
+        PEAK.load(AtomicOrdering::Relaxed)
+    }
+
+    pub fn reset_peak() {
+        PEAK.store(CountingAllocator::in_use(), AtomicOrdering::Relaxed);
+    }
+
+    fn grew(size: usize) {
+        ALLOCATED.fetch_add(size, AtomicOrdering::Relaxed);
+        let in_use = IN_USE.fetch_add(size, AtomicOrdering::Relaxed) + size;
+        PEAK.fetch_max(in_use, AtomicOrdering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        CountingAllocator::grew(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), AtomicOrdering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            CountingAllocator::grew(new_size - layout.size());
+        } else {
+            IN_USE.fetch_sub(layout.size() - new_size, AtomicOrdering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+const SEARCH_COLUMNS: [&str; 10] =
+    ["search", "workload", "queries", "hits", "mean_ns", "median_ns", "p99_ns", "mean_probes", "max_probes", "mean_jumps"];
+
+fn search_row(result: &SearchResult) -> [String; 10] {
+    [
+        result.search.to_string(),
+        result.workload.to_string(),
+        result.queries.to_string(),
+        result.hits.to_string(),
+        result.mean.as_nanos().to_string(),
+        result.median.as_nanos().to_string(),
+        result.p99.as_nanos().to_string(),
+        format!("{:.2}", result.mean_probes),
+        result.max_probes.to_string(),
+        format!("{:.2}", result.mean_jumps),
+    ]
+}
+
+/// Writes search benchmark results in `format`.
+pub fn write_search_report<W: Write>(results: &[SearchResult], format: ReportFormat, writer: W) -> io::Result<()> {
+    write_report(&SEARCH_COLUMNS, 2, results.iter().map(search_row), format, writer)
+}
+
+const SORT_COLUMNS: [&str; 8] = ["algorithm", "input", "size", "threads", "mean_ms", "min_ms", "comparisons", "peak_bytes"];
+
+fn sort_row(result: &SortResult) -> [String; 8] {
+    [
+        result.algorithm.to_string(),
+        result.input.to_string(),
+        result.size.to_string(),
+        result.threads.to_string(),
+        format!("{:.3}", result.mean.as_secs_f64() * 1e3),
+        format!("{:.3}", result.min.as_secs_f64() * 1e3),
+        result.comparisons.to_string(),
+        result.peak_memory.map_or_else(String::new, |bytes| bytes.to_string()),
+    ]
+}
+
+/// Writes sort benchmark results in `format`. Peak memory is left empty (or
+/// `null` in JSON) when it was not measured.
+pub fn write_sort_report<W: Write>(results: &[SortResult], format: ReportFormat, writer: W) -> io::Result<()> {
+    write_report(&SORT_COLUMNS, 2, results.iter().map(sort_row), format, writer)
+}
+
+// The first `text_columns` columns are quoted in JSON, the numeric ones after
+// them written as they are.
+fn write_report<W, I, const N: usize>(columns: &[&str; N], text_columns: usize, rows: I, format: ReportFormat, mut writer: W) -> io::Result<()>
+where
+    W: Write,
+    I: Iterator<Item = [String; N]>,
+{
+    let rows: Vec<[String; N]> = rows.collect();
+    match format {
+        ReportFormat::Table => {
+            let widths: Vec<usize> =
+                (0..N).map(|i| rows.iter().map(|row| row[i].len()).chain([columns[i].len()]).max().unwrap_or(0)).collect();
+            let line = |cells: &[&str]| -> String {
+                let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, &width)| format!("{:>width$}", cell)).collect();
+                padded.join("  ")
+            };
+            writeln!(writer, "{}", line(columns))?;
+            for row in &rows {
+                writeln!(writer, "{}", line(&row.each_ref().map(String::as_str)))?;
+            }
+        }
+        ReportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(writer);
+            csv.write_record(columns)?;
+            for row in &rows {
+                csv.write_record(row)?;
+            }
+            csv.flush()?;
+        }
+        ReportFormat::Json => {
+            writeln!(writer, "[")?;
+            for (i, row) in rows.iter().enumerate() {
+                let fields: Vec<String> = columns
+                    .iter()
+                    .zip(row)
+                    .enumerate()
+                    .map(|(i, (column, value))| {
+                        if i < text_columns {
+                            format!("\"{}\": \"{}\"", column, value.replace('\\', "\\\\").replace('"', "\\\""))
+                        } else if value.is_empty() {
+                            format!("\"{}\": null", column)
+                        } else {
+                            format!("\"{}\": {}", column, value)
+                        }
+                    })
+                    .collect();
+                let separator = if i + 1 < rows.len() { "," } else { "" };
+                writeln!(writer, "  {{{}}}{}", fields.join(", "), separator)?;
+            }
+            writeln!(writer, "]")?;
+        }
+    }
+    Ok(())
+}
+
+// SplitMix64: small, fast and good enough to spread benchmark inputs. Seeded,
+// so every run asks the same queries.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..bound; `bound` must not be 0.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next() as u128 * bound as u128) >> 64) as u64
+    }
+
+    // Uniform in [0, 1).
+    fn fraction(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
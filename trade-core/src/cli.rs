@@ -0,0 +1,190 @@
+// Command-line options shared by the PartA/PartB binaries. Each binary
+// defines its own subcommands and flattens these into them.
+
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::benchmark::{write_sort_report, ReportFormat, SortAlgorithm, SortBenchmark, SortInput};
+use crate::order::{Nulls, OrderKey, OrderSpec};
+use crate::search::{self, Search};
+use crate::{print_records, save_to_file, Column, Data, ErrorPolicy, Loader};
+
+#[derive(Debug, Clone, Args)]
+pub struct InputArgs {
+    /// CSV export to read (defaults to effects.csv, or cs.csv for the search tools)
+    #[arg(short, long, global = true)]
+    pub input: Option<PathBuf>,
+
+    /// What to do with malformed rows: fail-fast, skip or coerce
+    #[arg(long, global = true, default_value = "fail-fast")]
+    pub on_error: ErrorPolicy,
+}
+
+impl InputArgs {
+    /// Loads the records from `--input`, or from `default_path` when the flag
+    /// is absent. Rejected rows are reported on stderr.
+    pub fn load(&self, default_path: &str) -> Result<Vec<Data>, Box<dyn Error>> {
+        let report = self.loader().load(&self.path(default_path))?;
+        if !report.is_clean() {
+            eprintln!("{}", report.summary());
+        }
+        Ok(report.data)
+    }
+
+    /// `--input`, or `default_path` when the flag is absent.
+    pub fn path(&self, default_path: &str) -> String {
+        match &self.input {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => default_path.to_string(),
+        }
+    }
+
+    /// A loader configured with `--on-error`, for callers that stream the
+    /// records instead of loading them all.
+    pub fn loader(&self) -> Loader {
+        Loader::new().policy(self.on_error)
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OutputArgs {
+    /// Write the resulting records as CSV to this file instead of printing them
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    pub fn write(&self, data: &[Data]) -> Result<(), Box<dyn Error>> {
+        match &self.output {
+            Some(path) => save_to_file(data, &path.to_string_lossy())?,
+            None => print_records(data),
+        }
+        Ok(())
+    }
+}
+
+/// Field a sort command orders the records by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Trade date, ties broken by value
+    Date,
+    Value,
+    Cumulative,
+    Country,
+    Commodity,
+}
+
+impl SortKey {
+    /// The key as an `OrderSpec`. Dates keep unparsable dates first, as the
+    /// sorts always have.
+    pub fn order(self) -> OrderSpec {
+        let keys = match self {
+            SortKey::Date => vec![OrderKey::ascending(Column::Date).nulls(Nulls::First), OrderKey::ascending(Column::Value)],
+            SortKey::Value => vec![OrderKey::ascending(Column::Value)],
+            SortKey::Cumulative => vec![OrderKey::ascending(Column::Cumulative)],
+            SortKey::Country => vec![OrderKey::ascending(Column::Country)],
+            SortKey::Commodity => vec![OrderKey::ascending(Column::Commodity)],
+        };
+        OrderSpec::new(keys)
+    }
+}
+
+/// Point search a lookup command finds a date with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SearchAlgorithm {
+    Binary,
+    Interpolation,
+    /// Binary interpolation search
+    Bis,
+    /// Galloping search, fastest for dates near the start
+    Exponential,
+    Fibonacci,
+    Jump,
+    /// One interpolation probe, then a sequential scan
+    InterpolationSequential,
+}
+
+impl SearchAlgorithm {
+    pub fn search(self) -> &'static dyn Search {
+        match self {
+            SearchAlgorithm::Binary => &search::Binary,
+            SearchAlgorithm::Interpolation => &search::Interpolation,
+            SearchAlgorithm::Bis => &search::Bis,
+            SearchAlgorithm::Exponential => &search::Exponential,
+            SearchAlgorithm::Fibonacci => &search::Fibonacci,
+            SearchAlgorithm::Jump => &search::Jump,
+            SearchAlgorithm::InterpolationSequential => &search::InterpolationSequential,
+        }
+    }
+}
+
+/// Options of the sort tools' `bench` command.
+#[derive(Debug, Clone, Args)]
+pub struct SortBenchArgs {
+    /// Comma-separated sorts: counting, merge, heap, quick (default: all)
+    #[arg(short, long, value_delimiter = ',')]
+    pub algorithm: Vec<SortAlgorithm>,
+
+    /// Comma-separated inputs: random, sorted, reversed, duplicates, real (default: all)
+    #[arg(long, value_delimiter = ',')]
+    pub inputs: Vec<SortInput>,
+
+    /// Comma-separated record counts
+    #[arg(short, long, value_delimiter = ',', default_value = "1000,10000,100000")]
+    pub sizes: Vec<usize>,
+
+    /// Comma-separated thread counts (default: 1 and every core)
+    #[arg(short, long, value_delimiter = ',')]
+    pub threads: Vec<usize>,
+
+    /// Timed sorts per measurement
+    #[arg(long, default_value_t = 3)]
+    pub repeats: usize,
+
+    /// Seed for the generated inputs
+    #[arg(long, default_value_t = 0x5EED)]
+    pub seed: u64,
+
+    /// Report format: table, csv or json
+    #[arg(short, long, default_value = "table")]
+    pub format: ReportFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl SortBenchArgs {
+    /// The records `run` needs from `input`: all of them when the `real`
+    /// input is selected and none otherwise, so the export is only read when
+    /// it is used.
+    pub fn load(&self, input: &InputArgs, default_path: &str) -> Result<Vec<Data>, Box<dyn Error>> {
+        if self.inputs.is_empty() || self.inputs.contains(&SortInput::Real) {
+            input.load(default_path)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Runs the benchmark, drawing the `real` input from `data`, and writes
+    /// the report.
+    pub fn run(&self, data: &[Data]) -> Result<(), Box<dyn Error>> {
+        let algorithms = if self.algorithm.is_empty() { SortAlgorithm::ALL.to_vec() } else { self.algorithm.clone() };
+        let inputs = if self.inputs.is_empty() { SortInput::ALL.to_vec() } else { self.inputs.clone() };
+        let mut benchmark = SortBenchmark::new().sizes(self.sizes.clone()).repeats(self.repeats).seed(self.seed);
+        if !self.threads.is_empty() {
+            benchmark = benchmark.threads(self.threads.clone());
+        }
+
+        let results = benchmark.run(data, &algorithms, &inputs)?;
+        match &self.output {
+            Some(path) => write_sort_report(&results, self.format, File::create(path)?)?,
+            None => write_sort_report(&results, self.format, io::stdout().lock())?,
+        }
+        Ok(())
+    }
+}
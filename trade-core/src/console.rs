@@ -0,0 +1,30 @@
+use std::io::Write;
+
+use crate::Data;
+
+pub fn print_data(data: &Data) {
+    println!("{}", data);
+}
+
+pub fn print_records(data: &[Data]) {
+    for d in data {
+        print_data(d);
+    }
+
+    println!("--------------------------------");
+    println!("{} records", data.len());
+    println!("--------------------------------");
+}
+
+pub fn user_input() -> String {
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
+
+// Prints `message` without a trailing newline and reads the answer.
+pub fn prompt(message: &str) -> String {
+    print!("{}", message);
+    std::io::stdout().flush().unwrap();
+    user_input()
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::date::{DateError, TradeDate};
+
+/// One row of the trade CSV export.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Data {
+    pub direction: String,
+    pub year: u16,
+    pub date: String,
+    pub weekday: String,
+    pub country: String,
+    pub comodity: String,
+    pub transport_mode: String,
+    pub measure: String,
+    pub value: u64,
+    pub cumulative: u64,
+}
+
+impl Data {
+    pub fn new() -> Data {
+        Data::default()
+    }
+
+    pub fn trade_date(&self) -> Result<TradeDate, DateError> {
+        TradeDate::parse(&self.date)
+    }
+}
+
+impl fmt::Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+            self.direction,
+            self.year,
+            self.date,
+            self.weekday,
+            self.country,
+            self.comodity,
+            self.transport_mode,
+            self.measure,
+            self.value,
+            self.cumulative
+        )
+    }
+}
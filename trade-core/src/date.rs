@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// A calendar date as it appears in the trade export (`dd/mm/yyyy`).
+///
+/// Internally the date is stored as the number of days since 01/01/1970, so
+/// ordering, equality and day arithmetic are plain integer operations and
+/// month boundaries and leap years are handled correctly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TradeDate {
+    days: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateError {
+    /// The string is not three `/`-separated numbers.
+    Format(String),
+    /// The month is outside `1..=12`.
+    Month(u32),
+    /// The day does not exist in the given month.
+    Day { day: u32, month: u32, year: i32 },
+    /// The year is outside `0..=9999`.
+    OutOfRange(i32),
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateError::Format(input) => write!(f, "invalid date format '{}', expected dd/mm/yyyy", input),
+            DateError::Month(month) => write!(f, "invalid month {}", month),
+            DateError::Day { day, month, year } => write!(f, "invalid day {} for {:02}/{}", day, month, year),
+            DateError::OutOfRange(year) => write!(f, "year {} is out of range, expected 0 to 9999", year),
+        }
+    }
+}
+
+impl Error for DateError {}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+impl TradeDate {
+    /// Years a date may have; the day arithmetic stays far from overflowing.
+    pub const YEARS: RangeInclusive<i32> = 0..=9999;
+
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Result<TradeDate, DateError> {
+        if !TradeDate::YEARS.contains(&year) {
+            return Err(DateError::OutOfRange(year));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(DateError::Month(month));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(DateError::Day { day, month, year });
+        }
+
+        Ok(TradeDate { days: days_from_civil(year, month, day) })
+    }
+
+    pub fn parse(date_str: &str) -> Result<TradeDate, DateError> {
+        let format_error = || DateError::Format(date_str.to_string());
+        let mut parts = date_str.trim().split('/');
+        let day = parts.next().and_then(|p| p.parse::<u32>().ok()).ok_or_else(format_error)?;
+        let month = parts.next().and_then(|p| p.parse::<u32>().ok()).ok_or_else(format_error)?;
+        let year = parts.next().and_then(|p| p.parse::<i32>().ok()).ok_or_else(format_error)?;
+        if parts.next().is_some() {
+            return Err(format_error());
+        }
+
+        TradeDate::from_ymd(year, month, day)
+    }
+
+    /// Days since 01/01/1970 (negative before it).
+    pub fn from_days(days: i32) -> TradeDate {
+        TradeDate { days }
+    }
+
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    pub fn year(&self) -> i32 {
+        civil_from_days(self.days).0
+    }
+
+    pub fn month(&self) -> u32 {
+        civil_from_days(self.days).1
+    }
+
+    pub fn day(&self) -> u32 {
+        civil_from_days(self.days).2
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // 01/01/1970 was a Thursday.
+        match (self.days + 3).rem_euclid(7) {
+            0 => Weekday::Monday,
+            1 => Weekday::Tuesday,
+            2 => Weekday::Wednesday,
+            3 => Weekday::Thursday,
+            4 => Weekday::Friday,
+            5 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+}
+
+impl fmt::Display for TradeDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.days);
+        write!(f, "{:02}/{:02}/{:04}", day, month, year)
+    }
+}
+
+impl FromStr for TradeDate {
+    type Err = DateError;
+
+    fn from_str(s: &str) -> Result<TradeDate, DateError> {
+        TradeDate::parse(s)
+    }
+}
+
+impl Weekday {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "Monday",
+            Weekday::Tuesday => "Tuesday",
+            Weekday::Wednesday => "Wednesday",
+            Weekday::Thursday => "Thursday",
+            Weekday::Friday => "Friday",
+            Weekday::Saturday => "Saturday",
+            Weekday::Sunday => "Sunday",
+        }
+    }
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Converts a `dd/mm/yyyy` date into days since 01/01/1970 so it can be
+// compared or interpolated as an integer. Returns `None` for invalid dates.
+pub fn date_to_days(date_str: &str) -> Option<i32> {
+    TradeDate::parse(date_str).ok().map(|date| date.days())
+}
+
+// Howard Hinnant's `days_from_civil`: proleptic Gregorian calendar, the year
+// is shifted to start in March so the leap day is the last day of the year.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i32 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i32 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i32 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Works in i64 so that any `from_days` value converts without overflowing.
+fn civil_from_days(days: i32) -> (i32, u32, u32) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
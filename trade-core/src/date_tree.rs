@@ -0,0 +1,265 @@
+// A date-keyed multimap of trade records on top of `AvlTree`, shared by the
+// PartB tree tools: every node keeps the records of one date together with a
+// summary of their values, for totals and maxima over periods.
+
+use std::fmt;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+
+use crate::avl::{Aggregate, AvlTree};
+use crate::{Data, TradeDate};
+
+/// Every record of one date: the tree is a multimap, so records sharing a
+/// date (one per country, commodity and direction) live under the same key,
+/// in the order they were inserted. Records with an unreadable date sort
+/// first. Each node also summarizes the values of its subtree, for totals
+/// over periods.
+#[derive(Debug, Default)]
+pub struct DateTree {
+    dates: AvlTree<Option<TradeDate>, DateRecords, ValueSummary>,
+}
+
+// The records of one date along with the summary of their values, kept up to
+// date on every change so the tree never goes over the records again.
+#[derive(Debug)]
+struct DateRecords {
+    records: Vec<Data>,
+    summary: ValueSummary,
+}
+
+/// Count, total, smallest and largest `value` of a set of records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueSummary {
+    pub count: usize,
+    pub sum: u128,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl ValueSummary {
+    /// The summary of no records.
+    pub const EMPTY: ValueSummary = ValueSummary { count: 0, sum: 0, min: u64::MAX, max: 0 };
+
+    pub fn of(data: &Data) -> ValueSummary {
+        ValueSummary { count: 1, sum: data.value as u128, min: data.value, max: data.value }
+    }
+
+    pub fn of_all(records: &[Data]) -> ValueSummary {
+        records.iter().fold(ValueSummary::EMPTY, |summary, data| summary.combine(&ValueSummary::of(data)))
+    }
+}
+
+impl Aggregate<DateRecords> for ValueSummary {
+    fn from_value(date: &DateRecords) -> ValueSummary {
+        date.summary
+    }
+
+    fn combine(&self, other: &ValueSummary) -> ValueSummary {
+        ValueSummary {
+            count: self.count + other.count,
+            sum: self.sum + other.sum,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+impl DateRecords {
+    fn new(data: Data) -> DateRecords {
+        DateRecords { summary: ValueSummary::of(&data), records: vec![data] }
+    }
+
+    fn push(&mut self, data: Data) {
+        self.summary = self.summary.combine(&ValueSummary::of(&data));
+        self.records.push(data);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Data> {
+        if index >= self.records.len() {
+            return None;
+        }
+        let data = self.records.remove(index);
+        self.replaced(data.value, None);
+        Some(data)
+    }
+
+    fn set_value(&mut self, index: usize, value: u64) -> bool {
+        let Some(record) = self.records.get_mut(index) else {
+            return false;
+        };
+        let old = mem::replace(&mut record.value, value);
+        self.replaced(old, Some(value));
+        true
+    }
+
+    // Takes `old` out of the summary and `new` into it. Only losing the
+    // smallest or largest value needs the records again.
+    fn replaced(&mut self, old: u64, new: Option<u64>) {
+        if old == self.summary.min || old == self.summary.max {
+            self.summary = ValueSummary::of_all(&self.records);
+            return;
+        }
+        self.summary.count -= 1;
+        self.summary.sum -= old as u128;
+        if let Some(new) = new {
+            self.summary.count += 1;
+            self.summary.sum += new as u128;
+            self.summary.min = self.summary.min.min(new);
+            self.summary.max = self.summary.max.max(new);
+        }
+    }
+}
+
+/// Narrows the records of a date down to one. Fields left out match any
+/// record; text is compared case-insensitively.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct RecordFilter {
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub direction: Option<String>,
+
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub country: Option<String>,
+
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub commodity: Option<String>,
+
+    /// Only needed when the records differ in measure alone ($ or Tonnes)
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub measure: Option<String>,
+}
+
+impl RecordFilter {
+    pub fn matches(&self, data: &Data) -> bool {
+        let field = |filter: &Option<String>, value: &str| filter.as_ref().is_none_or(|f| f.trim().eq_ignore_ascii_case(value.trim()));
+        field(&self.direction, &data.direction)
+            && field(&self.country, &data.country)
+            && field(&self.commodity, &data.comodity)
+            && field(&self.measure, &data.measure)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SelectError {
+    DateNotFound,
+    NoMatch,
+    /// The filter matches this many of the date's records.
+    Ambiguous(usize),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectError::DateNotFound => write!(f, "Date not found"),
+            SelectError::NoMatch => write!(f, "No record on that date matches"),
+            SelectError::Ambiguous(n) => write!(f, "{} records on that date match; narrow it down with --direction, --country, --commodity or --measure", n),
+        }
+    }
+}
+
+impl DateTree {
+    pub fn new() -> DateTree {
+        DateTree::default()
+    }
+
+    pub fn insert(&mut self, data: &Data) {
+        let key = data.trade_date().ok();
+        if self.dates.modify(&key, |date| date.push(data.clone())).is_none() {
+            self.dates.insert(key, DateRecords::new(data.clone()));
+        }
+    }
+
+    /// Every record in date order, records of one date in insertion order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Data> {
+        self.dates.iter().flat_map(|(_, date)| &date.records)
+    }
+
+    /// The records dated within `dates`, in date order, without visiting the
+    /// rest of the tree.
+    pub fn range(&self, dates: impl RangeBounds<TradeDate>) -> impl DoubleEndedIterator<Item = &Data> {
+        self.dates.range(key_bounds(dates)).flat_map(|(_, date)| &date.records)
+    }
+
+    /// The summary of the values of the records dated within `dates`, from
+    /// O(log n) stored subtree summaries.
+    pub fn summary(&self, dates: impl RangeBounds<TradeDate>) -> ValueSummary {
+        self.dates.aggregate(key_bounds(dates)).unwrap_or(ValueSummary::EMPTY)
+    }
+
+    /// Total value of the records dated from `from` to `to`, both included.
+    pub fn sum_value(&self, from: TradeDate, to: TradeDate) -> u128 {
+        self.summary(from..=to).sum
+    }
+
+    /// Largest value among the records dated from `from` to `to`, both
+    /// included; `None` when there are none.
+    pub fn max_value(&self, from: TradeDate, to: TradeDate) -> Option<u64> {
+        let summary = self.summary(from..=to);
+        (summary.count > 0).then_some(summary.max)
+    }
+
+    /// The closest dates in the tree before and after `date`.
+    pub fn neighbours(&self, date: TradeDate) -> (Option<TradeDate>, Option<TradeDate>) {
+        let before = self.dates.predecessor(&Some(date)).and_then(|(&key, _)| key);
+        let after = self.dates.successor(&Some(date)).and_then(|(&key, _)| key);
+        (before, after)
+    }
+
+    /// Height of the underlying AVL tree.
+    pub fn height(&self) -> usize {
+        self.dates.height()
+    }
+
+    pub fn records(&self) -> Vec<Data> {
+        self.iter().cloned().collect()
+    }
+
+    /// Every record on the date, in insertion order.
+    pub fn search(&self, date: TradeDate) -> Option<&[Data]> {
+        self.dates.get(&Some(date)).map(|date| date.records.as_slice())
+    }
+
+    /// Position, among the records on the date, of the one record `filter`
+    /// matches.
+    pub fn select(&self, date: TradeDate, filter: &RecordFilter) -> Result<usize, SelectError> {
+        let records = self.search(date).ok_or(SelectError::DateNotFound)?;
+        let mut matches = records.iter().enumerate().filter(|(_, d)| filter.matches(d)).map(|(i, _)| i);
+        match (matches.next(), matches.count()) {
+            (None, _) => Err(SelectError::NoMatch),
+            (Some(index), 0) => Ok(index),
+            (Some(_), others) => Err(SelectError::Ambiguous(others + 1)),
+        }
+    }
+
+    /// Removes the `index`-th record on the date, and the date itself once
+    /// it holds no more records. `None` if there is no such record.
+    pub fn delete(&mut self, date: TradeDate, index: usize) -> Option<Data> {
+        let key = Some(date);
+        let (removed, emptied) = self.dates.modify(&key, |date| {
+            let removed = date.remove(index);
+            (removed, date.records.is_empty())
+        })?;
+        if emptied {
+            self.dates.remove(&key);
+        }
+        removed
+    }
+
+    /// Sets the value of the `index`-th record on the date. Returns false
+    /// when there is no such record.
+    pub fn edit(&mut self, date: TradeDate, index: usize, value: u64) -> bool {
+        let edited = self.dates.modify(&Some(date), |date| date.set_value(index, value));
+        edited.unwrap_or(false)
+    }
+}
+
+// Date bounds as tree keys. Unreadable dates sort first under `None`; keep
+// them out of ranges open at the start.
+fn key_bounds(dates: impl RangeBounds<TradeDate>) -> (Bound<Option<TradeDate>>, Bound<Option<TradeDate>>) {
+    let start = match dates.start_bound() {
+        Bound::Included(&date) => Bound::Included(Some(date)),
+        Bound::Excluded(&date) => Bound::Excluded(Some(date)),
+        Bound::Unbounded => Bound::Excluded(None),
+    };
+    (start, dates.end_bound().map(|&date| Some(date)))
+}
@@ -0,0 +1,233 @@
+// External merge sort for exports that do not fit in memory. The input is read
+// in runs of at most `run_len` records, each run is sorted with the parallel
+// merge sort and spilled to an anonymous temporary file, and the runs are then
+// merged through a priority queue of their head records. At most `fan_in` runs
+// are merged at once; more runs are merged in several passes.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use crate::heap::PriorityQueue;
+use crate::loader::{write_record, Records, HEADER};
+use crate::sort::merge_sort_by;
+use crate::{write_data, Data, LoadError, Loader};
+
+#[derive(Debug)]
+pub enum ExternalSortError {
+    /// Reading the input (or a spilled run) failed.
+    Load(LoadError),
+    /// Writing a run or the output failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for ExternalSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalSortError::Load(e) => write!(f, "{}", e),
+            ExternalSortError::Io(e) => write!(f, "external sort I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for ExternalSortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExternalSortError::Load(e) => Some(e),
+            ExternalSortError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<LoadError> for ExternalSortError {
+    fn from(e: LoadError) -> ExternalSortError {
+        ExternalSortError::Load(e)
+    }
+}
+
+impl From<io::Error> for ExternalSortError {
+    fn from(e: io::Error) -> ExternalSortError {
+        ExternalSortError::Io(e)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExternalSortReport {
+    pub records: usize,
+    /// Sorted runs spilled to disk; 0 when the input fit in a single run.
+    pub runs: usize,
+    pub merge_passes: usize,
+    pub rejected: Vec<LoadError>,
+    pub coerced: Vec<LoadError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternalSort {
+    run_len: usize,
+    fan_in: usize,
+    temp_dir: Option<PathBuf>,
+    loader: Loader,
+}
+
+impl Default for ExternalSort {
+    fn default() -> ExternalSort {
+        ExternalSort {
+            run_len: 100_000,
+            fan_in: 64,
+            temp_dir: None,
+            loader: Loader::new(),
+        }
+    }
+}
+
+impl ExternalSort {
+    pub fn new() -> ExternalSort {
+        ExternalSort::default()
+    }
+
+    /// Records held in memory and sorted at a time (at least 1).
+    pub fn run_len(mut self, records: usize) -> ExternalSort {
+        self.run_len = records.max(1);
+        self
+    }
+
+    /// Runs merged at once (at least 2). Each open run costs one record and a
+    /// 64 KB read buffer.
+    pub fn fan_in(mut self, runs: usize) -> ExternalSort {
+        self.fan_in = runs.max(2);
+        self
+    }
+
+    /// Directory for the spilled runs instead of the system temp directory.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> ExternalSort {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Loader (error policy, column aliases) used to read the input.
+    pub fn loader(mut self, loader: Loader) -> ExternalSort {
+        self.loader = loader;
+        self
+    }
+
+    pub fn sort_file<F>(&self, input: &str, output: &str, compare: F) -> Result<ExternalSortReport, ExternalSortError>
+    where
+        F: Fn(&Data, &Data) -> Ordering + Sync,
+    {
+        let records = self.loader.records(input)?;
+        let writer = BufWriter::new(File::create(output)?);
+        self.sort_records(records, writer, compare)
+    }
+
+    /// Sorts a record stream (see `Loader::records`) into `writer` as CSV. The
+    /// sort is stable.
+    pub fn sort_records<R, W, F>(&self, mut records: Records<R>, mut writer: W, compare: F) -> Result<ExternalSortReport, ExternalSortError>
+    where
+        R: Read,
+        W: Write,
+        F: Fn(&Data, &Data) -> Ordering + Sync,
+    {
+        let mut report = ExternalSortReport::default();
+        let mut runs = Vec::new();
+        let mut chunk = Vec::new();
+        loop {
+            chunk.clear();
+            for record in records.by_ref().take(self.run_len) {
+                chunk.push(record?);
+            }
+            if chunk.is_empty() {
+                break;
+            }
+            report.records += chunk.len();
+            merge_sort_by(&mut chunk, &compare);
+
+            // Everything fit in one run: no need to touch the disk.
+            if runs.is_empty() && chunk.len() < self.run_len {
+                write_data(&chunk, &mut writer)?;
+                (report.rejected, report.coerced) = records.into_errors();
+                return Ok(report);
+            }
+            runs.push(self.spill(&chunk)?);
+        }
+        drop(chunk);
+        (report.rejected, report.coerced) = records.into_errors();
+        report.runs = runs.len();
+
+        while runs.len() > self.fan_in {
+            report.merge_passes += 1;
+            let mut merged = Vec::new();
+            let mut remaining = runs.into_iter();
+            loop {
+                let group: Vec<File> = remaining.by_ref().take(self.fan_in).collect();
+                if group.is_empty() {
+                    break;
+                }
+                let mut run = BufWriter::new(self.temp_file()?);
+                writeln!(run, "{}", HEADER)?;
+                merge_runs(group, &mut run, &compare)?;
+                merged.push(rewind(run)?);
+            }
+            runs = merged;
+        }
+
+        writeln!(writer, "{}", HEADER)?;
+        if !runs.is_empty() {
+            report.merge_passes += 1;
+            merge_runs(runs, &mut writer, &compare)?;
+        }
+        writer.flush()?;
+        Ok(report)
+    }
+
+    fn spill(&self, chunk: &[Data]) -> Result<File, ExternalSortError> {
+        let mut run = BufWriter::new(self.temp_file()?);
+        write_data(chunk, &mut run)?;
+        rewind(run)
+    }
+
+    // Anonymous files are removed by the OS once closed, even on a panic.
+    fn temp_file(&self) -> io::Result<File> {
+        match &self.temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir),
+            None => tempfile::tempfile(),
+        }
+    }
+}
+
+fn rewind(run: BufWriter<File>) -> Result<File, ExternalSortError> {
+    let mut file = run.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// k-way merge of sorted runs into `writer` (records only, no header).
+fn merge_runs<W, F>(runs: Vec<File>, writer: &mut W, compare: &F) -> Result<(), ExternalSortError>
+where
+    W: Write,
+    F: Fn(&Data, &Data) -> Ordering,
+{
+    let mut readers = runs
+        .into_iter()
+        .map(|run| Loader::new().records_from_reader(run, "sorted run"))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The queue pops its greatest entry, so compare in reverse; on ties the
+    // earlier run wins, which keeps the merge stable.
+    let mut heads = PriorityQueue::new(|a: &(Data, usize), b: &(Data, usize)| compare(&b.0, &a.0).then(b.1.cmp(&a.1)));
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(record) = reader.next() {
+            heads.push((record?, run));
+        }
+    }
+
+    while let Some((record, run)) = heads.pop() {
+        write_record(&record, writer)?;
+        if let Some(next) = readers[run].next() {
+            heads.push((next?, run));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+// A d-ary max-heap over any element type, ordered by a comparator. The same
+// iterative sift functions back both the owned `PriorityQueue` and the in-place
+// heap sorts in `sort`.
+
+use std::cmp::Ordering;
+
+/// A `BinaryHeap`-like priority queue whose order comes from a comparator
+/// instead of `Ord`, so the same record type can be queued by value, by
+/// cumulative value or by any composite key. `pop` returns the greatest
+/// element according to `compare`. Each node has `arity` children (2 by
+/// default); wider heaps are shallower, trading comparisons for fewer levels.
+pub struct PriorityQueue<T, F> {
+    data: Vec<T>,
+    arity: usize,
+    compare: F,
+}
+
+impl<T, F> PriorityQueue<T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    pub fn new(compare: F) -> PriorityQueue<T, F> {
+        PriorityQueue::with_arity(2, compare)
+    }
+
+    /// # Panics
+    /// If `arity` is less than 2.
+    pub fn with_arity(arity: usize, compare: F) -> PriorityQueue<T, F> {
+        assert!(arity >= 2, "a heap needs at least two children per node");
+        PriorityQueue { data: Vec::new(), arity, compare }
+    }
+
+    /// Builds a queue from `data` in O(n).
+    pub fn from_vec(data: Vec<T>, arity: usize, compare: F) -> PriorityQueue<T, F> {
+        let mut queue = PriorityQueue::with_arity(arity, compare);
+        queue.data = data;
+        build_heap(&mut queue.data, queue.arity, &queue.compare);
+        queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        let last = self.data.len() - 1;
+        sift_up(&mut self.data, last, self.arity, &self.compare);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let item = self.data.swap_remove(0);
+        sift_down(&mut self.data, 0, self.arity, &self.compare);
+        Some(item)
+    }
+
+    /// Pushes `item` and pops the greatest element in one sift, which is what
+    /// a bounded top-k buffer does for every new record.
+    pub fn push_pop(&mut self, mut item: T) -> T {
+        if let Some(top) = self.data.first_mut() {
+            if (self.compare)(top, &item) == Ordering::Greater {
+                std::mem::swap(top, &mut item);
+                sift_down(&mut self.data, 0, self.arity, &self.compare);
+            }
+        }
+        item
+    }
+
+    /// The elements in ascending order according to `compare`.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            sift_down_bottom_up(&mut self.data[..end], self.arity, &self.compare);
+        }
+        self.data
+    }
+
+    /// The elements in heap order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+/// The `k` greatest items according to `compare`, greatest first, keeping at
+/// most `k` items in memory. `items` can be a streaming iterator such as a CSV
+/// reader, so the whole dataset never has to be held at once.
+pub fn top_k_by<T, I, F>(items: I, k: usize, compare: F) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(&T, &T) -> Ordering,
+{
+    if k == 0 {
+        return Vec::new();
+    }
+
+    // A min-heap of the best k so far: its top is the weakest candidate.
+    let mut best = PriorityQueue::with_arity(2, |a: &T, b: &T| compare(b, a));
+    for item in items {
+        if best.len() < k {
+            best.push(item);
+        } else {
+            best.push_pop(item);
+        }
+    }
+    best.into_sorted_vec()
+}
+
+pub fn top_k_by_key<T, I, K, F>(items: I, k: usize, key: F) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    top_k_by(items, k, |a, b| key(a).cmp(&key(b)))
+}
+
+/// Rearranges `data` into a max-heap, sifting every parent down from the last
+/// one to the root.
+pub(crate) fn build_heap<T, F>(data: &mut [T], arity: usize, compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    if data.len() <= 1 {
+        return;
+    }
+    for i in (0..=(data.len() - 2) / arity).rev() {
+        sift_down(data, i, arity, compare);
+    }
+}
+
+/// Moves `data[i]` down until it is not smaller than any of its children.
+pub(crate) fn sift_down<T, F>(data: &mut [T], mut i: usize, arity: usize, compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    loop {
+        let first = i * arity + 1;
+        if first >= data.len() {
+            return;
+        }
+
+        let mut largest = i;
+        for child in first..(first + arity).min(data.len()) {
+            if compare(&data[child], &data[largest]) == Ordering::Greater {
+                largest = child;
+            }
+        }
+        if largest == i {
+            return;
+        }
+        data.swap(i, largest);
+        i = largest;
+    }
+}
+
+/// Bottom-up sift of the root: follow the larger children down to a leaf
+/// without comparing against the sinking element, then sift it back up. After
+/// a heap sort swap the root is usually one of the smallest elements, so this
+/// needs about half the comparisons of `sift_down`.
+pub(crate) fn sift_down_bottom_up<T, F>(data: &mut [T], arity: usize, compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut i = 0;
+    loop {
+        let first = i * arity + 1;
+        if first >= data.len() {
+            break;
+        }
+
+        let mut largest = first;
+        for child in first + 1..(first + arity).min(data.len()) {
+            if compare(&data[child], &data[largest]) == Ordering::Greater {
+                largest = child;
+            }
+        }
+        data.swap(i, largest);
+        i = largest;
+    }
+    sift_up(data, i, arity, compare);
+}
+
+fn sift_up<T, F>(data: &mut [T], mut i: usize, arity: usize, compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    while i > 0 {
+        let parent = (i - 1) / arity;
+        if compare(&data[i], &data[parent]) != Ordering::Greater {
+            return;
+        }
+        data.swap(i, parent);
+        i = parent;
+    }
+}
@@ -0,0 +1,27 @@
+// Shared building blocks for the PartA/PartB tools: the trade record, the
+// CSV loader and the date helpers every binary used to carry its own copy of.
+
+pub mod argsort;
+pub mod avl;
+pub mod benchmark;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod console;
+pub mod data;
+pub mod date;
+pub mod date_tree;
+pub mod external;
+pub mod heap;
+pub mod loader;
+pub mod order;
+pub mod search;
+pub mod select;
+pub mod sort;
+
+#[cfg(test)]
+mod tests;
+
+pub use console::{print_data, print_records, prompt, user_input};
+pub use data::Data;
+pub use date::{date_to_days, DateError, TradeDate, Weekday};
+pub use loader::{read_data, save_to_file, write_data, Column, ErrorPolicy, LoadError, LoadReport, Loader, Records, RowPosition};
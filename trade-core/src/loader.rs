@@ -0,0 +1,538 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use csv::{ByteRecord, ReaderBuilder};
+
+use crate::{Data, TradeDate};
+
+pub const HEADER: &str = "Direction,Year,Date,Weekday,Country,Commodity,Transport_Mode,Measure,Value,Cumulative";
+
+/// The fields of a `Data` record, in the order of the portal's export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    Direction,
+    Year,
+    Date,
+    Weekday,
+    Country,
+    Commodity,
+    TransportMode,
+    Measure,
+    Value,
+    Cumulative,
+}
+
+impl Column {
+    pub const ALL: [Column; 10] = [
+        Column::Direction,
+        Column::Year,
+        Column::Date,
+        Column::Weekday,
+        Column::Country,
+        Column::Commodity,
+        Column::TransportMode,
+        Column::Measure,
+        Column::Value,
+        Column::Cumulative,
+    ];
+
+    /// The header name used by the export.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Direction => "Direction",
+            Column::Year => "Year",
+            Column::Date => "Date",
+            Column::Weekday => "Weekday",
+            Column::Country => "Country",
+            Column::Commodity => "Commodity",
+            Column::TransportMode => "Transport_Mode",
+            Column::Measure => "Measure",
+            Column::Value => "Value",
+            Column::Cumulative => "Cumulative",
+        }
+    }
+}
+
+// Header names are compared case-insensitively and without surrounding
+// whitespace, so `value` and ` Value ` both map to `Column::Value`.
+fn normalize_header(name: &str) -> String {
+    name.trim().trim_start_matches('\u{feff}').to_lowercase()
+}
+
+// CSV column index of every `Column`, resolved from the header row.
+#[derive(Debug, Clone, Copy)]
+struct ColumnMap {
+    index: [usize; 10],
+}
+
+impl ColumnMap {
+    fn get(&self, column: Column) -> usize {
+        self.index[column as usize]
+    }
+}
+
+/// Where a record starts in the input. `row` counts data rows from 1 (the
+/// header is row 0), `line` is the 1-based line in the file and `byte` the
+/// offset of the first byte of the record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RowPosition {
+    pub row: u64,
+    pub line: u64,
+    pub byte: u64,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be opened or read.
+    Io { path: String, source: io::Error },
+    /// The header row lacks columns the record needs.
+    MissingColumns(Vec<&'static str>),
+    /// The row could not be split into fields (e.g. wrong number of columns).
+    Malformed { at: RowPosition, message: String },
+    /// A field could not be parsed into its column type.
+    Field {
+        at: RowPosition,
+        column: usize,
+        name: &'static str,
+        value: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for RowPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {} (line {}, byte {})", self.row, self.line, self.byte)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => write!(f, "{}: {}", path, source),
+            LoadError::MissingColumns(names) => write!(f, "missing required columns: {}", names.join(", ")),
+            LoadError::Malformed { at, message } => write!(f, "{}: {}", at, message),
+            LoadError::Field { at, column, name, value, message } => {
+                write!(f, "{}, column {} ({}): {} '{}'", at, column + 1, name, message, value)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl LoadError {
+    pub fn position(&self) -> Option<RowPosition> {
+        match self {
+            LoadError::Io { .. } | LoadError::MissingColumns(_) => None,
+            LoadError::Malformed { at, .. } | LoadError::Field { at, .. } => Some(*at),
+        }
+    }
+}
+
+/// What the loader does with a row it cannot turn into a `Data`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Stop at the first bad row and return its error.
+    #[default]
+    FailFast,
+    /// Drop bad rows and list them in `LoadReport::rejected`.
+    Skip,
+    /// Replace unparsable numeric fields with 0 and keep the row; the
+    /// replaced fields are listed in `LoadReport::coerced`. Rows that cannot
+    /// be split into fields at all, or whose date does not parse, are still
+    /// rejected.
+    Coerce,
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorPolicy, String> {
+        match s {
+            "fail-fast" => Ok(ErrorPolicy::FailFast),
+            "skip" => Ok(ErrorPolicy::Skip),
+            "coerce" => Ok(ErrorPolicy::Coerce),
+            _ => Err(format!("unknown error policy '{}', expected fail-fast, skip or coerce", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub data: Vec<Data>,
+    pub rejected: Vec<LoadError>,
+    pub coerced: Vec<LoadError>,
+}
+
+impl LoadReport {
+    pub fn is_clean(&self) -> bool {
+        self.rejected.is_empty() && self.coerced.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} records loaded, {} rows rejected, {} fields coerced",
+            self.data.len(),
+            self.rejected.len(),
+            self.coerced.len()
+        );
+        for e in self.rejected.iter().chain(self.coerced.iter()) {
+            summary.push_str("\n  ");
+            summary.push_str(&e.to_string());
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    policy: ErrorPolicy,
+    aliases: HashMap<String, Column>,
+}
+
+impl Loader {
+    pub fn new() -> Loader {
+        Loader::default()
+    }
+
+    pub fn policy(mut self, policy: ErrorPolicy) -> Loader {
+        self.policy = policy;
+        self
+    }
+
+    /// Accepts `header` as the name of `column`, for exports where a column
+    /// has been renamed. The canonical name keeps working as well.
+    pub fn alias(mut self, header: &str, column: Column) -> Loader {
+        self.aliases.insert(normalize_header(header), column);
+        self
+    }
+
+    // Columns are matched by header name; extra columns are ignored and the
+    // first occurrence of a duplicated column wins.
+    fn column_map(&self, headers: &ByteRecord) -> Result<ColumnMap, LoadError> {
+        let mut index = [None; 10];
+        for (i, name) in headers.iter().enumerate() {
+            let name = normalize_header(&String::from_utf8_lossy(name));
+            let column = Column::ALL
+                .iter()
+                .copied()
+                .find(|c| normalize_header(c.name()) == name)
+                .or_else(|| self.aliases.get(&name).copied());
+            if let Some(column) = column {
+                index[column as usize].get_or_insert(i);
+            }
+        }
+
+        let missing: Vec<&'static str> = Column::ALL
+            .iter()
+            .filter(|c| index[**c as usize].is_none())
+            .map(|c| c.name())
+            .collect();
+        if !missing.is_empty() {
+            return Err(LoadError::MissingColumns(missing));
+        }
+
+        Ok(ColumnMap {
+            index: index.map(|i| i.unwrap_or_default()),
+        })
+    }
+
+    pub fn load(&self, filename: &str) -> Result<LoadReport, LoadError> {
+        let file = File::open(filename).map_err(|source| LoadError::Io {
+            path: filename.to_string(),
+            source,
+        })?;
+        self.load_from_reader(file, filename)
+    }
+
+    // `name` is only used to label I/O errors.
+    pub fn load_from_reader<R: Read>(&self, reader: R, name: &str) -> Result<LoadReport, LoadError> {
+        let mut records = self.records_from_reader(reader, name)?;
+        let mut data = Vec::with_capacity(111_438); // Preallocate memory based on an estimate
+        for record in &mut records {
+            data.push(record?);
+        }
+
+        Ok(LoadReport {
+            data,
+            rejected: records.rejected,
+            coerced: records.coerced,
+        })
+    }
+
+    /// Streams the records of `filename` one at a time instead of collecting
+    /// them, for files that do not fit in memory.
+    pub fn records(&self, filename: &str) -> Result<Records<File>, LoadError> {
+        let file = File::open(filename).map_err(|source| LoadError::Io {
+            path: filename.to_string(),
+            source,
+        })?;
+        self.records_from_reader(file, filename)
+    }
+
+    pub fn records_from_reader<R: Read>(&self, reader: R, name: &str) -> Result<Records<R>, LoadError> {
+        let mut rdr = ReaderBuilder::new()
+            .buffer_capacity(1 << 16) // Set buffer capacity to 64 KB
+            .has_headers(true) // The header row drives the column mapping
+            .delimiter(b',') // Change this if your CSV uses a different delimiter
+            .quote(b'"') // Change this if your CSV uses a different quoting character
+            .double_quote(true) // Set this to false if your CSV doesn't use double quote escaping
+            .flexible(false) // Set this to true if your CSV has a variable number of fields per record
+            .from_reader(reader);
+        let headers = match rdr.byte_headers() {
+            Ok(headers) => headers.clone(),
+            Err(e) => return Err(csv_error(e, name).unwrap_or_else(|e| e)),
+        };
+        let columns = self.column_map(&headers)?;
+
+        Ok(Records {
+            rdr,
+            columns,
+            policy: self.policy,
+            name: name.to_string(),
+            record: ByteRecord::new(),
+            rejected: Vec::new(),
+            coerced: Vec::new(),
+            done: false,
+        })
+    }
+}
+
+/// Iterator over the records of one CSV export, see `Loader::records`.
+///
+/// Under `ErrorPolicy::FailFast` the first bad row is yielded as an error and
+/// ends the iteration. Under `Skip` and `Coerce` bad rows are not yielded but
+/// kept in `rejected()`/`coerced()`. I/O errors always end the iteration.
+pub struct Records<R> {
+    rdr: csv::Reader<R>,
+    columns: ColumnMap,
+    policy: ErrorPolicy,
+    name: String,
+    record: ByteRecord,
+    rejected: Vec<LoadError>,
+    coerced: Vec<LoadError>,
+    done: bool,
+}
+
+impl<R> Records<R> {
+    pub fn rejected(&self) -> &[LoadError] {
+        &self.rejected
+    }
+
+    pub fn coerced(&self) -> &[LoadError] {
+        &self.coerced
+    }
+
+    pub fn into_errors(self) -> (Vec<LoadError>, Vec<LoadError>) {
+        (self.rejected, self.coerced)
+    }
+
+    // Returns the error if it has to be yielded (fail-fast), or keeps it.
+    fn reject(&mut self, error: LoadError) -> Option<LoadError> {
+        match self.policy {
+            ErrorPolicy::FailFast => {
+                self.done = true;
+                Some(error)
+            }
+            ErrorPolicy::Skip | ErrorPolicy::Coerce => {
+                self.rejected.push(error);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Result<Data, LoadError>;
+
+    fn next(&mut self) -> Option<Result<Data, LoadError>> {
+        while !self.done {
+            match self.rdr.read_byte_record(&mut self.record) {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    break;
+                }
+                Err(e) => match csv_error(e, &self.name) {
+                    Ok(error) => match self.reject(error) {
+                        Some(error) => return Some(Err(error)),
+                        None => continue,
+                    },
+                    Err(fatal) => {
+                        self.done = true;
+                        return Some(Err(fatal));
+                    }
+                },
+            }
+
+            let at = record_position(&self.record);
+            let policy = self.policy;
+            let mut coerced = Vec::new();
+            let parsed = parse_record(&self.record, &self.columns, at, |error| {
+                if policy == ErrorPolicy::Coerce {
+                    coerced.push(error);
+                    Ok(())
+                } else {
+                    Err(error)
+                }
+            });
+
+            match parsed {
+                Ok(data) => {
+                    self.coerced.append(&mut coerced);
+                    return Some(Ok(data));
+                }
+                Err(error) => {
+                    if let Some(error) = self.reject(error) {
+                        return Some(Err(error));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn read_data(filename: &str) -> Result<Vec<Data>, LoadError> {
+    Loader::new().load(filename).map(|report| report.data)
+}
+
+fn row_position(pos: &csv::Position) -> RowPosition {
+    RowPosition {
+        row: pos.record(),
+        line: pos.line(),
+        byte: pos.byte(),
+    }
+}
+
+fn record_position(record: &ByteRecord) -> RowPosition {
+    record.position().map(row_position).unwrap_or_default()
+}
+
+// Splits csv errors into the ones that only affect the current row and the
+// ones (I/O) that end the load.
+fn csv_error(error: csv::Error, name: &str) -> Result<LoadError, LoadError> {
+    if !error.is_io_error() {
+        return Ok(LoadError::Malformed {
+            at: error.position().map(row_position).unwrap_or_default(),
+            message: error.to_string(),
+        });
+    }
+    match error.into_kind() {
+        csv::ErrorKind::Io(source) => Err(LoadError::Io {
+            path: name.to_string(),
+            source,
+        }),
+        _ => unreachable!("is_io_error() checked above"),
+    }
+}
+
+// `on_error` decides whether a bad numeric field aborts the row (returns the
+// error) or is coerced to 0 (returns `Ok`).
+fn parse_record<F>(record: &ByteRecord, columns: &ColumnMap, at: RowPosition, mut on_error: F) -> Result<Data, LoadError>
+where
+    F: FnMut(LoadError) -> Result<(), LoadError>,
+{
+    let text = |column: Column| String::from_utf8_lossy(&record[columns.get(column)]).into_owned();
+
+    // Records are searched and sorted by date, so there is no value to coerce
+    // a bad date to: the row is rejected whatever the policy.
+    let date = text(Column::Date);
+    if let Err(e) = TradeDate::parse(&date) {
+        return Err(LoadError::Field {
+            at,
+            column: columns.get(Column::Date),
+            name: Column::Date.name(),
+            value: date,
+            message: e.to_string(),
+        });
+    }
+
+    Ok(Data {
+        direction: text(Column::Direction),
+        year: parse_field(record, columns, at, Column::Year, &mut on_error)?,
+        date,
+        weekday: text(Column::Weekday),
+        country: text(Column::Country),
+        comodity: text(Column::Commodity),
+        transport_mode: text(Column::TransportMode),
+        measure: text(Column::Measure),
+        value: parse_field(record, columns, at, Column::Value, &mut on_error)?,
+        cumulative: parse_field(record, columns, at, Column::Cumulative, &mut on_error)?,
+    })
+}
+
+fn parse_field<T, F>(record: &ByteRecord, columns: &ColumnMap, at: RowPosition, column: Column, on_error: &mut F) -> Result<T, LoadError>
+where
+    T: FromStr + Default,
+    T::Err: fmt::Display,
+    F: FnMut(LoadError) -> Result<(), LoadError>,
+{
+    let index = columns.get(column);
+    let value = String::from_utf8_lossy(&record[index]);
+    match value.parse::<T>() {
+        Ok(n) => Ok(n),
+        Err(e) => {
+            on_error(LoadError::Field {
+                at,
+                column: index,
+                name: column.name(),
+                value: value.to_string(),
+                message: e.to_string(),
+            })?;
+            Ok(T::default())
+        }
+    }
+}
+
+pub fn write_data<W: Write>(data: &[Data], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+    for d in data {
+        write_record(d, &mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Writes one record as a CSV row, without the header.
+pub fn write_record<W: Write>(d: &Data, writer: &mut W) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{},{},{},{},{},{},{},{},{},{}",
+        csv_field(d.direction.as_str()),
+        d.year,
+        csv_field(d.date.as_str()),
+        csv_field(d.weekday.as_str()),
+        csv_field(d.country.as_str()),
+        csv_field(d.comodity.as_str()),
+        csv_field(d.transport_mode.as_str()),
+        csv_field(d.measure.as_str()),
+        d.value,
+        d.cumulative
+    )
+}
+
+pub fn save_to_file(data: &[Data], filename: &str) -> io::Result<()> {
+    write_data(data, io::BufWriter::new(File::create(filename)?))
+}
+
+// Quotes a field that holds a delimiter, a quote or a line break, doubling
+// any quotes inside it, so the reader gets back the same field.
+fn csv_field(string: &str) -> Cow<'_, str> {
+    if string.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", string.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(string)
+    }
+}
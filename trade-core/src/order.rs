@@ -0,0 +1,237 @@
+// Multi-key orderings of trade records. An `OrderSpec` lists the columns to
+// sort by, each ascending or descending and with its own placement of missing
+// values, and parses from strings such as `country,date:desc,value`. Every
+// sort in `trade_core::sort` takes `|a, b| spec.compare(a, b)`; counting and
+// radix sort take `spec.integer_passes()` instead.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::sort::days_to_key;
+use crate::{Column, Data};
+
+/// Where records with a missing value go: an empty text field or a date that
+/// does not parse. Numeric columns are never missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Nulls {
+    First,
+    #[default]
+    Last,
+}
+
+/// One column of an `OrderSpec`. `nulls` is applied after `descending`, so
+/// `Nulls::Last` puts missing values last in both directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderKey {
+    pub column: Column,
+    pub descending: bool,
+    pub nulls: Nulls,
+}
+
+impl OrderKey {
+    pub fn ascending(column: Column) -> OrderKey {
+        OrderKey { column, descending: false, nulls: Nulls::Last }
+    }
+
+    pub fn descending(column: Column) -> OrderKey {
+        OrderKey { column, descending: true, nulls: Nulls::Last }
+    }
+
+    pub fn nulls(mut self, nulls: Nulls) -> OrderKey {
+        self.nulls = nulls;
+        self
+    }
+
+    pub fn compare(&self, a: &Data, b: &Data) -> Ordering {
+        match self.column {
+            Column::Year => self.directed(a.year.cmp(&b.year)),
+            Column::Value => self.directed(a.value.cmp(&b.value)),
+            Column::Cumulative => self.directed(a.cumulative.cmp(&b.cumulative)),
+            Column::Date => self.compare_nullable(a.trade_date().ok(), b.trade_date().ok()),
+            column => self.compare_nullable(text(column, a), text(column, b)),
+        }
+    }
+
+    /// A `u64` that orders records the same way as `compare`, for counting
+    /// and radix sort, or `None` for text columns.
+    pub fn integer_key(&self) -> Option<impl Fn(&Data) -> u64 + Sync> {
+        match self.column {
+            Column::Year | Column::Date | Column::Value | Column::Cumulative => {
+                let key = *self;
+                Some(move |d: &Data| key.integer(d))
+            }
+            _ => None,
+        }
+    }
+
+    fn integer(&self, d: &Data) -> u64 {
+        let number = match self.column {
+            Column::Year => d.year as u64,
+            Column::Value => d.value,
+            Column::Cumulative => d.cumulative,
+            _ => {
+                // Day keys fit in 32 bits, which leaves room for the two
+                // null slots at either end.
+                let Ok(date) = d.trade_date() else {
+                    return match self.nulls {
+                        Nulls::First => 0,
+                        Nulls::Last => u64::MAX,
+                    };
+                };
+                let day = days_to_key(date.days());
+                return 1 + if self.descending { u32::MAX as u64 - day } else { day };
+            }
+        };
+        if self.descending {
+            u64::MAX - number
+        } else {
+            number
+        }
+    }
+
+    fn directed(&self, ordering: Ordering) -> Ordering {
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn compare_nullable<K: Ord>(&self, a: Option<K>, b: Option<K>) -> Ordering {
+        let null_first = match self.nulls {
+            Nulls::First => Ordering::Less,
+            Nulls::Last => Ordering::Greater,
+        };
+        match (a, b) {
+            (Some(a), Some(b)) => self.directed(a.cmp(&b)),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => null_first,
+            (Some(_), None) => null_first.reverse(),
+        }
+    }
+}
+
+// The text of a string column, `None` when it is empty.
+fn text(column: Column, d: &Data) -> Option<&str> {
+    let value = match column {
+        Column::Direction => &d.direction,
+        Column::Weekday => &d.weekday,
+        Column::Country => &d.country,
+        Column::Commodity => &d.comodity,
+        Column::TransportMode => &d.transport_mode,
+        Column::Measure => &d.measure,
+        _ => unreachable!("{} is not a text column", column.name()),
+    };
+    Some(value.as_str()).filter(|s| !s.trim().is_empty())
+}
+
+impl fmt::Display for OrderKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.column.name().to_lowercase())?;
+        if self.descending {
+            write!(f, ":desc")?;
+        }
+        if self.nulls == Nulls::First {
+            write!(f, ":nulls-first")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OrderKey {
+    type Err = String;
+
+    /// `column[:asc|:desc][:nulls-first|:nulls-last]`, case-insensitive.
+    fn from_str(s: &str) -> Result<OrderKey, String> {
+        let mut parts = s.split(':').map(|part| part.trim().to_lowercase().replace('-', "_"));
+        let name = parts.next().unwrap_or_default();
+        let column = Column::ALL
+            .into_iter()
+            .find(|column| column.name().to_lowercase() == name)
+            .ok_or_else(|| format!("unknown column `{}` in sort order", s.trim()))?;
+
+        let mut key = OrderKey::ascending(column);
+        for modifier in parts {
+            match modifier.as_str() {
+                "asc" => key.descending = false,
+                "desc" => key.descending = true,
+                "nulls_first" => key.nulls = Nulls::First,
+                "nulls_last" => key.nulls = Nulls::Last,
+                _ => return Err(format!("unknown modifier `{}` for {} (expected asc, desc, nulls-first or nulls-last)", modifier, name)),
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// An ordering over several columns: records are compared by the first key,
+/// ties by the next one, and so on. With `stable` set, records equal on every
+/// key also keep their input order, whichever algorithm sorts them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderSpec {
+    keys: Vec<OrderKey>,
+    stable: bool,
+}
+
+impl OrderSpec {
+    pub fn new(keys: Vec<OrderKey>) -> OrderSpec {
+        OrderSpec { keys, stable: false }
+    }
+
+    /// Requires equal records to keep their input order. The merge, counting
+    /// and radix sorts are stable anyway; heap and quick sort then sort record
+    /// positions with the position as a final tie-break.
+    pub fn stable(mut self, stable: bool) -> OrderSpec {
+        self.stable = stable;
+        self
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.stable
+    }
+
+    pub fn keys(&self) -> &[OrderKey] {
+        &self.keys
+    }
+
+    pub fn compare(&self, a: &Data, b: &Data) -> Ordering {
+        self.keys
+            .iter()
+            .map(|key| key.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Integer keys for a series of stable counting or radix sort passes,
+    /// least significant first, so that after the last pass the records are in
+    /// `compare` order. `None` if any key is a text column.
+    pub fn integer_passes(&self) -> Option<Vec<impl Fn(&Data) -> u64 + Sync>> {
+        self.keys.iter().rev().map(|key| key.integer_key()).collect()
+    }
+}
+
+impl fmt::Display for OrderSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OrderSpec {
+    type Err = String;
+
+    /// A comma-separated list of `OrderKey`s, e.g. `country,date:desc,value`.
+    fn from_str(s: &str) -> Result<OrderSpec, String> {
+        if s.trim().is_empty() {
+            return Err("empty sort order".to_string());
+        }
+        let keys = s.split(',').map(str::parse).collect::<Result<Vec<_>, _>>()?;
+        Ok(OrderSpec::new(keys))
+    }
+}
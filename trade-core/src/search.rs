@@ -0,0 +1,555 @@
+// Bound searches over records sorted by a key. A date usually has many rows
+// (one per country, commodity and direction), so instead of one arbitrary
+// matching index these return where the run of equal keys starts and ends,
+// or the whole slice of records in a key range. The point searches (binary,
+// interpolation, BIS, exponential, Fibonacci, jump) implement `Search`, so the
+// tools can swap them and compare their probe counts.
+
+use std::cmp::Ordering;
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::sort::days_to_key;
+use crate::{Data, TradeDate};
+
+/// Index of the first element whose key is not less than `key`, or
+/// `data.len()` if there is none. `data` must be sorted by `f`.
+pub fn lower_bound_by_key<T, K, F>(data: &[T], key: &K, f: F) -> usize
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    data.partition_point(|x| f(x) < *key)
+}
+
+/// Index of the first element whose key is greater than `key`, or
+/// `data.len()` if there is none.
+pub fn upper_bound_by_key<T, K, F>(data: &[T], key: &K, f: F) -> usize
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    data.partition_point(|x| f(x) <= *key)
+}
+
+/// The positions of every element whose key equals `key`; empty (at the
+/// insertion point) when there is none.
+pub fn equal_range_by_key<T, K, F>(data: &[T], key: &K, f: F) -> Range<usize>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    let start = lower_bound_by_key(data, key, &f);
+    start..start + upper_bound_by_key(&data[start..], key, &f)
+}
+
+/// Widens a match at `index`, as found by any point search, to the positions
+/// of every element with the same key. Only the elements on either side of
+/// `index` are searched.
+///
+/// # Panics
+/// If `index` is out of bounds.
+pub fn equal_range_around<T, K, F>(data: &[T], index: usize, f: F) -> Range<usize>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    let key = f(&data[index]);
+    lower_bound_by_key(&data[..index], &key, &f)..index + upper_bound_by_key(&data[index..], &key, &f)
+}
+
+/// The positions of the elements whose key lies in `range`, e.g. `from..=to`.
+pub fn bounds_by_key<T, K, R, F>(data: &[T], range: R, f: F) -> Range<usize>
+where
+    K: Ord,
+    R: RangeBounds<K>,
+    F: Fn(&T) -> K,
+{
+    let start = match range.start_bound() {
+        Bound::Included(key) => lower_bound_by_key(data, key, &f),
+        Bound::Excluded(key) => upper_bound_by_key(data, key, &f),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(key) => upper_bound_by_key(data, key, &f),
+        Bound::Excluded(key) => lower_bound_by_key(data, key, &f),
+        Bound::Unbounded => data.len(),
+    };
+    start..end.max(start)
+}
+
+/// The elements whose key lies in `range`.
+pub fn range_by_key<T, K, R, F>(data: &[T], range: R, f: F) -> &[T]
+where
+    K: Ord,
+    R: RangeBounds<K>,
+    F: Fn(&T) -> K,
+{
+    &data[bounds_by_key(data, range, f)]
+}
+
+/// What a search cost: `probes` is the number of keys read and `jumps` the
+/// number of steps a search took in strides (jump, exponential and binary
+/// interpolation search). Searches add to it, so one value can total a whole
+/// workload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub probes: usize,
+    pub jumps: usize,
+}
+
+/// The ascending integer keys a `Search` runs over. Every read goes through
+/// `key`, which counts it as a probe.
+pub struct Probes<'a> {
+    len: usize,
+    key_at: &'a dyn Fn(usize) -> u64,
+    stats: &'a mut SearchStats,
+}
+
+impl Probes<'_> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn key(&mut self, index: usize) -> u64 {
+        self.stats.probes += 1;
+        (self.key_at)(index)
+    }
+
+    pub fn jump(&mut self) {
+        self.stats.jumps += 1;
+    }
+}
+
+/// A point search over sorted integer keys. `find` returns `Ok` with the
+/// position of a key equal to `key` (any of them, if it repeats) or `Err`
+/// with the position where it would be inserted, as `slice::binary_search`
+/// does.
+pub trait Search: Sync {
+    fn name(&self) -> &'static str;
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize>;
+}
+
+/// Runs `search` for `key` among elements sorted by the integer key `f`,
+/// adding its cost to `stats`.
+pub fn search_by_key<T, F>(search: &dyn Search, data: &[T], key: u64, f: F, stats: &mut SearchStats) -> Result<usize, usize>
+where
+    F: Fn(&T) -> u64,
+{
+    let key_at = |i: usize| f(&data[i]);
+    search.find(&mut Probes { len: data.len(), key_at: &key_at, stats }, key)
+}
+
+/// Runs `search` for a record on `date`, in records sorted by date. The
+/// interpolating searches work on day numbers, so their estimates are
+/// calendar-correct.
+pub fn search_date(search: &dyn Search, data: &[Data], date: TradeDate, stats: &mut SearchStats) -> Result<usize, usize> {
+    search_by_key(search, data, days_to_key(date.days()), day_key, stats)
+}
+
+/// Every search, in the order the tools list them.
+pub static SEARCHES: [&dyn Search; 7] = [
+    &Binary,
+    &Interpolation,
+    &Bis,
+    &Exponential,
+    &Fibonacci,
+    &Jump,
+    &InterpolationSequential,
+];
+
+/// Plain binary search: O(log n) probes whatever the key distribution.
+pub struct Binary;
+
+impl Search for Binary {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        binary_search_in(keys, 0, keys.len(), key)
+    }
+}
+
+// Binary search of keys[low..end].
+fn binary_search_in(keys: &mut Probes<'_>, mut low: usize, mut end: usize, key: u64) -> Result<usize, usize> {
+    while low < end {
+        let mid = low + (end - low) / 2;
+        match keys.key(mid).cmp(&key) {
+            Ordering::Equal => return Ok(mid),
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => end = mid,
+        }
+    }
+    Err(low)
+}
+
+// Position of `key` in the window [low, high] by linear interpolation
+// between the window's first and last keys, which must differ and bracket
+// `key`. Computed in `u128`, so neither the subtraction nor the product can
+// overflow, and the result always lies inside the window.
+fn interpolate(low: usize, high: usize, low_key: u64, high_key: u64, key: u64) -> usize {
+    let offset = (key - low_key) as u128 * (high - low) as u128 / (high_key - low_key) as u128;
+    low + offset as usize
+}
+
+/// Interpolation search. A window whose keys are all equal is answered
+/// directly instead of dividing by zero, and whenever an interpolation probe
+/// fails to halve the window the next probe bisects it, so skewed keys cost
+/// O(log n) probes rather than O(n). Evenly spread keys take O(log log n).
+pub struct Interpolation;
+
+impl Search for Interpolation {
+    fn name(&self) -> &'static str {
+        "interpolation"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        if keys.is_empty() {
+            return Err(0);
+        }
+
+        // The window is keys[low..end]; its first and last keys are kept so
+        // each step reads only the probe and the one bound that moved.
+        let (mut low, mut end) = (0, keys.len());
+        let (mut low_key, mut high_key) = (keys.key(low), keys.key(end - 1));
+        let mut bisect = false;
+        loop {
+            if key < low_key {
+                return Err(low);
+            }
+            if key > high_key {
+                return Err(end);
+            }
+            if low_key == high_key {
+                return Ok(low);
+            }
+
+            let window = end - low;
+            let mid = if bisect {
+                low + window / 2
+            } else {
+                interpolate(low, end - 1, low_key, high_key, key)
+            };
+            match keys.key(mid).cmp(&key) {
+                Ordering::Equal => return Ok(mid),
+                Ordering::Less => {
+                    low = mid + 1;
+                    if low == end {
+                        return Err(low);
+                    }
+                    low_key = keys.key(low);
+                }
+                Ordering::Greater => {
+                    end = mid;
+                    if low == end {
+                        return Err(low);
+                    }
+                    high_key = keys.key(end - 1);
+                }
+            }
+            bisect = !bisect && end - low > window / 2;
+        }
+    }
+}
+
+/// Binary interpolation search (BIS): each round interpolates a probe inside
+/// the current window `[left, right]`, then jumps from it in steps of
+/// `sqrt(right - left + 1)` towards the key until it is bracketed; the
+/// bracket becomes the next window. Windows of up to 3 keys are scanned.
+/// Expected O(log log n) rounds on evenly spread keys.
+pub struct Bis;
+
+impl Search for Bis {
+    fn name(&self) -> &'static str {
+        "bis"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        if keys.is_empty() {
+            return Err(0);
+        }
+
+        let (mut left, mut right) = (0, keys.len() - 1);
+        loop {
+            let size = right - left + 1;
+            if size <= 3 {
+                for i in left..=right {
+                    match keys.key(i).cmp(&key) {
+                        Ordering::Equal => return Ok(i),
+                        Ordering::Greater => return Err(i),
+                        Ordering::Less => {}
+                    }
+                }
+                return Err(right + 1);
+            }
+
+            // A key outside the window is missing; the previous round only
+            // guarantees the keys just outside it are below and above `key`.
+            let (left_key, right_key) = (keys.key(left), keys.key(right));
+            if key < left_key {
+                return Err(left);
+            }
+            if key > right_key {
+                return Err(right + 1);
+            }
+            if left_key == right_key {
+                return Ok(left);
+            }
+            let next = interpolate(left, right, left_key, right_key, key);
+            let step = size.isqrt();
+
+            match keys.key(next).cmp(&key) {
+                Ordering::Equal => return Ok(next),
+                Ordering::Less => {
+                    // Jump right until a key is no longer below `key`.
+                    let mut i = 1;
+                    while next + i * step < right && keys.key(next + i * step) < key {
+                        keys.jump();
+                        i += 1;
+                    }
+                    left = next + (i - 1) * step + 1;
+                    right = right.min(next + i * step);
+                }
+                Ordering::Greater => {
+                    // Jump left until a key is no longer above `key`.
+                    let mut i = 1;
+                    while next >= left + i * step && keys.key(next - i * step) > key {
+                        keys.jump();
+                        i += 1;
+                    }
+                    right = next - (i - 1) * step - 1;
+                    left = left.max(next.saturating_sub(i * step));
+                }
+            }
+        }
+    }
+}
+
+/// Exponential (galloping) search: probes positions 1, 2, 4, 8, ... until it
+/// passes the key, then binary searches the last gap. O(log i) probes for a
+/// key at position `i`, so queries near the start are cheap.
+pub struct Exponential;
+
+impl Search for Exponential {
+    fn name(&self) -> &'static str {
+        "exponential"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        if keys.is_empty() {
+            return Err(0);
+        }
+        match keys.key(0).cmp(&key) {
+            Ordering::Equal => return Ok(0),
+            Ordering::Greater => return Err(0),
+            Ordering::Less => {}
+        }
+
+        // keys[bound / 2] < key on every iteration.
+        let mut bound = 1;
+        while bound < keys.len() && keys.key(bound) < key {
+            keys.jump();
+            bound *= 2;
+        }
+        binary_search_in(keys, bound / 2 + 1, keys.len().min(bound + 1), key)
+    }
+}
+
+/// Fibonacci search: like binary search, but the window is split at
+/// Fibonacci numbers, so the split points need only additions. O(log n)
+/// probes.
+pub struct Fibonacci;
+
+impl Search for Fibonacci {
+    fn name(&self) -> &'static str {
+        "fibonacci"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        // (f2, f1, f0) are three consecutive Fibonacci numbers F(k-2),
+        // F(k-1), F(k) with F(k) >= the window keys[low..end].
+        let (mut f2, mut f1, mut f0) = (0usize, 1usize, 1usize);
+        while f0 < keys.len() {
+            (f2, f1, f0) = (f1, f0, f1 + f0);
+        }
+
+        let (mut low, mut end) = (0, keys.len());
+        while low < end {
+            if f0 <= 1 {
+                // A single key left.
+                return match keys.key(low).cmp(&key) {
+                    Ordering::Equal => Ok(low),
+                    Ordering::Less => Err(low + 1),
+                    Ordering::Greater => Err(low),
+                };
+            }
+
+            let i = low + f1 - 1;
+            let ordering = if i < end { keys.key(i).cmp(&key) } else { Ordering::Greater };
+            match ordering {
+                Ordering::Equal => return Ok(i),
+                // Keep keys[i + 1..], at most F(k-2) of them.
+                Ordering::Less => {
+                    low = i + 1;
+                    (f2, f1, f0) = (2 * f2 - f1, f1 - f2, f2);
+                }
+                // Keep keys[low..i], at most F(k-1) of them.
+                Ordering::Greater => {
+                    end = end.min(i);
+                    (f2, f1, f0) = (f1 - f2, f2, f1);
+                }
+            }
+        }
+        Err(low)
+    }
+}
+
+/// Jump search: steps through the keys `sqrt(n)` at a time until it passes
+/// the key, then scans the last block. O(sqrt n) probes.
+pub struct Jump;
+
+impl Search for Jump {
+    fn name(&self) -> &'static str {
+        "jump"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        let step = keys.len().isqrt().max(1);
+
+        // Every key before `start` is below `key`.
+        let mut start = 0;
+        while start + step <= keys.len() && keys.key(start + step - 1) < key {
+            keys.jump();
+            start += step;
+        }
+        linear_search_in(keys, start, keys.len().min(start + step), key)
+    }
+}
+
+/// Interpolation-sequential search: a single interpolation probe, then a
+/// sequential scan from it towards the key. Cheap when keys are evenly
+/// spread, so the estimate lands a few places from the key; O(n) when not.
+pub struct InterpolationSequential;
+
+impl Search for InterpolationSequential {
+    fn name(&self) -> &'static str {
+        "interpolation-sequential"
+    }
+
+    fn find(&self, keys: &mut Probes<'_>, key: u64) -> Result<usize, usize> {
+        if keys.is_empty() {
+            return Err(0);
+        }
+        let high = keys.len() - 1;
+        let (low_key, high_key) = (keys.key(0), keys.key(high));
+        if key <= low_key {
+            return if key == low_key { Ok(0) } else { Err(0) };
+        }
+        if key >= high_key {
+            return if key == high_key { Ok(high) } else { Err(high + 1) };
+        }
+
+        let mut i = interpolate(0, high, low_key, high_key, key);
+        match keys.key(i).cmp(&key) {
+            Ordering::Equal => Ok(i),
+            Ordering::Less => linear_search_in(keys, i + 1, high, key),
+            Ordering::Greater => {
+                // keys[high] > key, so the scan stops at a key not above it.
+                loop {
+                    i -= 1;
+                    match keys.key(i).cmp(&key) {
+                        Ordering::Equal => return Ok(i),
+                        Ordering::Less => return Err(i + 1),
+                        Ordering::Greater => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Scans keys[start..end] upwards; every key before `start` is below `key`
+// and every key from `end` on is above it.
+fn linear_search_in(keys: &mut Probes<'_>, start: usize, end: usize, key: u64) -> Result<usize, usize> {
+    for i in start..end {
+        match keys.key(i).cmp(&key) {
+            Ordering::Equal => return Ok(i),
+            Ordering::Greater => return Err(i),
+            Ordering::Less => {}
+        }
+    }
+    Err(end)
+}
+
+/// Interpolation search for `key` among elements sorted by the integer key
+/// `f`: the position of an element equal to `key`, or `None`.
+pub fn interpolation_search_by_key<T, F>(data: &[T], key: u64, f: F) -> Option<usize>
+where
+    F: Fn(&T) -> u64,
+{
+    search_by_key(&Interpolation, data, key, f, &mut SearchStats::default()).ok()
+}
+
+/// Binary interpolation search for `key` among elements sorted by the
+/// integer key `f`.
+pub fn bis_by_key<T, F>(data: &[T], key: u64, f: F) -> Result<usize, usize>
+where
+    F: Fn(&T) -> u64,
+{
+    search_by_key(&Bis, data, key, f, &mut SearchStats::default())
+}
+
+/// Interpolation search for a record on `date`, in records sorted by date.
+pub fn interpolation_search_date(data: &[Data], date: TradeDate) -> Option<usize> {
+    search_date(&Interpolation, data, date, &mut SearchStats::default()).ok()
+}
+
+/// Binary interpolation search for a record on `date`, in records sorted by
+/// date.
+pub fn bis_date(data: &[Data], date: TradeDate) -> Result<usize, usize> {
+    search_date(&Bis, data, date, &mut SearchStats::default())
+}
+
+// Unparsable dates map to 0, the same place `date_key` sorts them.
+fn day_key(d: &Data) -> u64 {
+    d.trade_date().map_or(0, |date| days_to_key(date.days()))
+}
+
+// Records sorted by date put the unparsable dates first, before every real
+// date, which is also where `None` sorts.
+fn date_key(d: &Data) -> Option<TradeDate> {
+    d.trade_date().ok()
+}
+
+/// Every record on `date`, in records sorted by date.
+pub fn records_on(data: &[Data], date: TradeDate) -> &[Data] {
+    &data[equal_range_by_key(data, &Some(date), date_key)]
+}
+
+/// The positions of the records dated within `range` (e.g. `from..=to` or
+/// `from..`), in records sorted by date. Records with unparsable dates are
+/// never included.
+pub fn date_bounds<R>(data: &[Data], range: R) -> Range<usize>
+where
+    R: RangeBounds<TradeDate>,
+{
+    let start = match range.start_bound().map(|&date| Some(date)) {
+        Bound::Unbounded => Bound::Excluded(None),
+        bound => bound,
+    };
+    let end = range.end_bound().map(|&date| Some(date));
+    bounds_by_key(data, (start, end), date_key)
+}
+
+/// The records dated within `range`, in records sorted by date.
+pub fn records_between<R>(data: &[Data], range: R) -> &[Data]
+where
+    R: RangeBounds<TradeDate>,
+{
+    &data[date_bounds(data, range)]
+}
@@ -0,0 +1,184 @@
+// Selection queries: the k largest or smallest records, the n-th record, the
+// median and percentiles, all in expected linear time instead of a full sort.
+// The queries work on a vector of positions, so the input is left untouched
+// and only the selected records are cloned.
+
+use std::cmp::Ordering;
+
+use rayon::prelude::*;
+
+use crate::sort::{
+    choose_pivot, heap_sort_by, insertion_sort, partition, quick_sort_by, INSERTION_SORT_THRESHOLD, SEQUENTIAL_THRESHOLD,
+};
+
+/// Introselect: reorders `data` so that `data[n]` is the element a full sort
+/// would put there, everything before it compares less or equal and
+/// everything after it greater or equal. Uses the quick sort's pivot choice
+/// and three-way partition, and falls back to heap sort if the recursion gets
+/// too deep, so it is O(n) on average and O(n log n) in the worst case.
+///
+/// # Panics
+/// If `n >= data.len()`.
+pub fn select_nth_by<T, F>(data: &mut [T], n: usize, compare: F) -> &mut T
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    assert!(n < data.len(), "select index {} out of range for {} elements", n, data.len());
+
+    let mut depth_limit = 2 * (usize::BITS - data.len().leading_zeros()) as usize;
+    let mut slice = &mut data[..];
+    let mut n_in_slice = n;
+    loop {
+        if slice.len() <= INSERTION_SORT_THRESHOLD {
+            insertion_sort(slice, &compare);
+            break;
+        }
+        if depth_limit == 0 {
+            heap_sort_by(slice, &compare);
+            break;
+        }
+        depth_limit -= 1;
+
+        let pivot_index = choose_pivot(slice, &compare);
+        let (lt, gt) = partition(slice, pivot_index, &compare);
+        if n_in_slice < lt {
+            slice = &mut slice[..lt];
+        } else if n_in_slice >= gt {
+            slice = &mut slice[gt..];
+            n_in_slice -= gt;
+        } else {
+            break;
+        }
+    }
+
+    &mut data[n]
+}
+
+/// The `k` records with the largest key, largest first. Ties keep input order.
+/// Large inputs are split into chunks whose own top `k` are selected in
+/// parallel before the final selection.
+pub fn top_k<T, K, F>(data: &[T], k: usize, key: F) -> Vec<T>
+where
+    T: Clone + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    select_k(data, k, |&a, &b| key(&data[b]).cmp(&key(&data[a])).then(a.cmp(&b)))
+}
+
+/// The `k` records with the smallest key, smallest first. Ties keep input order.
+pub fn bottom_k<T, K, F>(data: &[T], k: usize, key: F) -> Vec<T>
+where
+    T: Clone + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    select_k(data, k, |&a, &b| key(&data[a]).cmp(&key(&data[b])).then(a.cmp(&b)))
+}
+
+/// The record that would be at index `n` (0-based) if `data` were sorted by
+/// `key`, or `None` if `n` is out of range. Inputs longer than
+/// `SEQUENTIAL_THRESHOLD` are first narrowed down with parallel partitions.
+pub fn nth_element<T, K, F>(data: &[T], n: usize, key: F) -> Option<T>
+where
+    T: Clone + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    if n >= data.len() {
+        return None;
+    }
+    let compare = |&a: &usize, &b: &usize| key(&data[a]).cmp(&key(&data[b])).then(a.cmp(&b));
+    let (mut order, n) = par_narrow((0..data.len()).collect(), n, &compare);
+    let index = *select_nth_by(&mut order, n, compare);
+    Some(data[index].clone())
+}
+
+/// The median record by `key`; for an even number of records, the lower of
+/// the two middle ones.
+pub fn median<T, K, F>(data: &[T], key: F) -> Option<T>
+where
+    T: Clone + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    nth_element(data, data.len().saturating_sub(1) / 2, key)
+}
+
+/// The record at the `percent`-th percentile by `key`, using the nearest-rank
+/// method: the smallest record with at least `percent`% of the records at or
+/// below it. `None` for empty input or a percentage outside `0..=100`.
+pub fn percentile<T, K, F>(data: &[T], percent: f64, key: F) -> Option<T>
+where
+    T: Clone + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    if data.is_empty() || !(0.0..=100.0).contains(&percent) {
+        return None;
+    }
+    let rank = (percent / 100.0 * data.len() as f64).ceil() as usize;
+    nth_element(data, rank.saturating_sub(1), key)
+}
+
+// Keeps only the positions on the side of a pivot that holds the `n`-th one,
+// comparing against the pivot in parallel, until `SEQUENTIAL_THRESHOLD` or
+// fewer are left; returns them with `n` shifted to match. Positions never
+// compare equal, so the pivot is alone in its class. After `2 * log2(len)`
+// rounds of bad pivots the rest is left to `select_nth_by`, which bounds the
+// worst case.
+fn par_narrow<F>(mut order: Vec<usize>, mut n: usize, compare: &F) -> (Vec<usize>, usize)
+where
+    F: Fn(&usize, &usize) -> Ordering + Sync,
+{
+    let mut rounds = 2 * (usize::BITS - order.len().leading_zeros()) as usize;
+    while order.len() > SEQUENTIAL_THRESHOLD && rounds > 0 {
+        rounds -= 1;
+        let pivot = order[choose_pivot(&order, compare)];
+        let sides: Vec<Ordering> = order.par_iter().map(|p| compare(p, &pivot)).collect();
+        let less = sides.par_iter().filter(|&&side| side == Ordering::Less).count();
+        let keep = match n.cmp(&less) {
+            Ordering::Equal => return (vec![pivot], 0),
+            Ordering::Less => Ordering::Less,
+            Ordering::Greater => {
+                n -= less + 1;
+                Ordering::Greater
+            }
+        };
+        order = order.into_par_iter().zip(sides).filter(|&(_, side)| side == keep).map(|(p, _)| p).collect();
+    }
+    (order, n)
+}
+
+// The first `k` positions in `compare` order, selected then sorted, mapped
+// back to records.
+fn select_k<T, F>(data: &[T], k: usize, compare: F) -> Vec<T>
+where
+    T: Clone,
+    F: Fn(&usize, &usize) -> Ordering + Sync,
+{
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    if order.len() > SEQUENTIAL_THRESHOLD && order.len() > 2 * k {
+        let chunk_len = order.len().div_ceil(rayon::current_num_threads()).max(2 * k);
+        order = order
+            .par_chunks_mut(chunk_len)
+            .flat_map_iter(|chunk| {
+                if chunk.len() > k {
+                    select_nth_by(chunk, k, &compare);
+                }
+                chunk[..k.min(chunk.len())].to_vec()
+            })
+            .collect();
+    }
+
+    if order.len() > k {
+        select_nth_by(&mut order, k, &compare);
+        order.truncate(k);
+    }
+    quick_sort_by(&mut order, &compare);
+    order.into_iter().map(|i| data[i].clone()).collect()
+}
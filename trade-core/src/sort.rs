@@ -0,0 +1,552 @@
+// Generic versions of the PartA sorting algorithms. Each algorithm comes in a
+// `_by` flavour taking a comparator and a `_by_key` flavour taking a key
+// extractor, in the spirit of `slice::sort_by`/`slice::sort_by_key`, so the
+// records can be ordered by any field or composite key.
+
+use std::cmp::Ordering;
+
+use rayon::prelude::*;
+
+use crate::heap::{build_heap, sift_down_bottom_up, PriorityQueue};
+
+/// Key ranges up to this many slots per record are counted directly; wider
+/// ranges are first compressed to the ranks of the distinct keys.
+const DIRECT_RANGE_PER_RECORD: u64 = 4;
+
+/// Stable counting sort over an integer key, linear in the number of records
+/// plus the key range. When `max - min` is much larger than the number of
+/// records (trade values span billions), the keys are compressed to their rank
+/// among the distinct keys first, so the count vector never exceeds the input.
+pub fn counting_sort_by_key<T, F>(data: &mut [T], key: F)
+where
+    F: Fn(&T) -> u64,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    let keys: Vec<u64> = data.iter().map(&key).collect();
+    let min_value = *keys.iter().min().unwrap();
+    let max_value = *keys.iter().max().unwrap();
+
+    let range = max_value - min_value;
+    let buckets: Vec<usize> = if range < (keys.len() as u64).saturating_mul(DIRECT_RANGE_PER_RECORD) {
+        keys.iter().map(|&k| (k - min_value) as usize).collect()
+    } else {
+        // Walk the positions in key order and give each new key the next
+        // rank, so the compression stays linear.
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        radix_sort_by_key(&mut order, |&i| keys[i]);
+        let mut ranks = vec![0; keys.len()];
+        let mut rank = 0;
+        for pair in order.windows(2) {
+            if keys[pair[1]] != keys[pair[0]] {
+                rank += 1;
+            }
+            ranks[pair[1]] = rank;
+        }
+        ranks
+    };
+
+    let bucket_count = buckets.iter().max().unwrap() + 1;
+    let mut count_vec = vec![0; bucket_count];
+    for &bucket in &buckets {
+        count_vec[bucket] += 1;
+    }
+
+    // Prefix sums: count_vec[b] becomes the first output slot of bucket b.
+    let mut total = 0;
+    for count in count_vec.iter_mut() {
+        let old_count = *count;
+        *count = total;
+        total += old_count;
+    }
+
+    let mut destination = Vec::with_capacity(buckets.len());
+    for &bucket in &buckets {
+        destination.push(count_vec[bucket]);
+        count_vec[bucket] += 1;
+    }
+
+    apply_destinations(data, destination);
+}
+
+/// Stable LSD radix sort over a `u64` key, one byte per pass. Passes where
+/// every key has the same byte are skipped, so small keys cost fewer passes.
+pub fn radix_sort_by_key<T, F>(data: &mut [T], key: F)
+where
+    F: Fn(&T) -> u64,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    let keys: Vec<u64> = data.iter().map(&key).collect();
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    let mut next = vec![0; keys.len()];
+
+    for shift in (0..64).step_by(8) {
+        let digit = |i: usize| ((keys[i] >> shift) & 0xff) as usize;
+
+        let mut count_vec = [0usize; 256];
+        for &i in &order {
+            count_vec[digit(i)] += 1;
+        }
+        if count_vec.contains(&order.len()) {
+            continue;
+        }
+
+        let mut total = 0;
+        for count in count_vec.iter_mut() {
+            let old_count = *count;
+            *count = total;
+            total += old_count;
+        }
+
+        for &i in &order {
+            let d = digit(i);
+            next[count_vec[d]] = i;
+            count_vec[d] += 1;
+        }
+        std::mem::swap(&mut order, &mut next);
+    }
+
+    apply_order(data, &order);
+}
+
+/// Radix sort over a day number (see `TradeDate::days`), which may be negative.
+pub fn radix_sort_by_days<T, F>(data: &mut [T], days: F)
+where
+    F: Fn(&T) -> i32,
+{
+    radix_sort_by_key(data, |d| days_to_key(days(d)));
+}
+
+/// Maps a day number to a `u64` with the same ordering, by flipping the sign
+/// bit so negative days come before positive ones.
+pub fn days_to_key(days: i32) -> u64 {
+    (days as u32 ^ 0x8000_0000) as u64
+}
+
+/// Sorts plain `u64`s in place.
+pub fn radix_sort_u64(data: &mut [u64]) {
+    radix_sort_by_key(data, |&n| n);
+}
+
+// Rearranges `data` so that position `p` holds the element that was at
+// `order[p]`.
+fn apply_order<T>(data: &mut [T], order: &[usize]) {
+    let mut destination = vec![0; order.len()];
+    for (position, &i) in order.iter().enumerate() {
+        destination[i] = position;
+    }
+    apply_destinations(data, destination);
+}
+
+// Moves every element to `destination[i]` by following the permutation's
+// cycles, so the records are swapped rather than cloned.
+pub(crate) fn apply_destinations<T>(data: &mut [T], mut destination: Vec<usize>) {
+    for i in 0..data.len() {
+        while destination[i] != i {
+            let j = destination[i];
+            data.swap(i, j);
+            destination.swap(i, j);
+        }
+    }
+}
+
+/// Stable parallel merge sort. The records are never moved while sorting: a
+/// permutation of their positions is merge sorted, ping-ponging between the
+/// permutation and one scratch buffer of positions, and the records are then
+/// swapped into place once. Halves longer than `SEQUENTIAL_THRESHOLD` are
+/// sorted and merged in parallel.
+pub fn merge_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    let records = &*data;
+    let compare_positions = |&a: &usize, &b: &usize| compare(&records[a], &records[b]);
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    let mut buffer = vec![0; data.len()];
+    merge_sort_par(&mut order, &mut buffer, &compare_positions);
+    apply_order(data, &order);
+}
+
+pub fn merge_sort_by_key<T, K, F>(data: &mut [T], key: F)
+where
+    T: Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    merge_sort_by(data, |a, b| key(a).cmp(&key(b)));
+}
+
+// Sorts `data` in place, using `buffer` (same length) as scratch. Each half is
+// sorted into the matching half of `buffer`, then merged back into `data`, so
+// nothing is copied back between levels.
+pub(crate) fn merge_sort_par<T, F>(data: &mut [T], buffer: &mut [T], compare: &F)
+where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if data.len() <= INSERTION_SORT_THRESHOLD {
+        insertion_sort(data, compare);
+        return;
+    }
+
+    let parallel = data.len() > SEQUENTIAL_THRESHOLD;
+    let mid = data.len() / 2;
+    let (left, right) = data.split_at_mut(mid);
+    let (left_buf, right_buf) = buffer.split_at_mut(mid);
+    join_if(
+        parallel,
+        || merge_sort_into(left, left_buf, compare),
+        || merge_sort_into(right, right_buf, compare),
+    );
+    par_merge(left_buf, right_buf, data, compare);
+}
+
+// Sorts `data` into `buffer`, leaving `data` as scratch.
+fn merge_sort_into<T, F>(data: &mut [T], buffer: &mut [T], compare: &F)
+where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if data.len() <= INSERTION_SORT_THRESHOLD {
+        insertion_sort(data, compare);
+        buffer.copy_from_slice(data);
+        return;
+    }
+
+    let parallel = data.len() > SEQUENTIAL_THRESHOLD;
+    let mid = data.len() / 2;
+    let (left, right) = data.split_at_mut(mid);
+    let (left_buf, right_buf) = buffer.split_at_mut(mid);
+    join_if(
+        parallel,
+        || merge_sort_par(left, left_buf, compare),
+        || merge_sort_par(right, right_buf, compare),
+    );
+    par_merge(left, right, buffer, compare);
+}
+
+fn join_if<A, B>(parallel: bool, a: A, b: B)
+where
+    A: FnOnce() + Send,
+    B: FnOnce() + Send,
+{
+    if parallel {
+        rayon::join(a, b);
+    } else {
+        a();
+        b();
+    }
+}
+
+// Merges the sorted runs `left` and `right` into `out`. Long merges are split
+// in two independent merges: the middle element of the longer run is located
+// in the other run by binary search, and both sides are merged in parallel.
+fn par_merge<T, F>(left: &[T], right: &[T], out: &mut [T], compare: &F)
+where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if out.len() <= SEQUENTIAL_THRESHOLD || left.is_empty() || right.is_empty() {
+        merge(left, right, out, compare);
+        return;
+    }
+
+    // Equal elements from `left` must stay in front of those from `right`.
+    let (left_mid, right_mid) = if left.len() >= right.len() {
+        let mid = left.len() / 2;
+        (mid, right.partition_point(|x| compare(x, &left[mid]) == Ordering::Less))
+    } else {
+        let mid = right.len() / 2;
+        (left.partition_point(|x| compare(x, &right[mid]) != Ordering::Greater), mid)
+    };
+
+    let (out_low, out_high) = out.split_at_mut(left_mid + right_mid);
+    rayon::join(
+        || par_merge(&left[..left_mid], &right[..right_mid], out_low, compare),
+        || par_merge(&left[left_mid..], &right[right_mid..], out_high, compare),
+    );
+}
+
+fn merge<T, F>(left: &[T], right: &[T], out: &mut [T], compare: &F)
+where
+    T: Copy,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let (mut left_idx, mut right_idx) = (0, 0);
+
+    for slot in out.iter_mut() {
+        // Taking from the left on ties keeps the sort stable.
+        let take_from_left = if left_idx < left.len() && right_idx < right.len() {
+            compare(&left[left_idx], &right[right_idx]) != Ordering::Greater
+        } else {
+            left_idx < left.len()
+        };
+
+        if take_from_left {
+            *slot = left[left_idx];
+            left_idx += 1;
+        } else {
+            *slot = right[right_idx];
+            right_idx += 1;
+        }
+    }
+}
+
+/// Heap sort with a binary max-heap, so the result is ascending.
+pub fn heap_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    heap_sort_with_arity(data, 2, compare);
+}
+
+pub fn heap_sort_by_key<T, K, F>(data: &mut [T], key: F)
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    heap_sort_by(data, |a, b| key(a).cmp(&key(b)));
+}
+
+/// Heap sort on a d-ary heap. Sifting is iterative, and the extraction phase
+/// uses bottom-up sifting (see `heap::sift_down_bottom_up`).
+///
+/// # Panics
+/// If `arity` is less than 2.
+pub fn heap_sort_with_arity<T, F>(data: &mut [T], arity: usize, compare: F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    assert!(arity >= 2, "a heap needs at least two children per node");
+    build_heap(data, arity, &compare);
+
+    for end in (1..data.len()).rev() {
+        data.swap(0, end);
+        sift_down_bottom_up(&mut data[..end], arity, &compare);
+    }
+}
+
+/// Parallel heap sort: one chunk per rayon thread is heap sorted in parallel,
+/// then a priority queue of the chunk heads merges the sorted runs.
+pub fn par_heap_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    T: Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let chunk_len = data.len().div_ceil(rayon::current_num_threads()).max(SEQUENTIAL_THRESHOLD);
+    if data.len() <= chunk_len {
+        heap_sort_by(data, compare);
+        return;
+    }
+
+    data.par_chunks_mut(chunk_len).for_each(|chunk| heap_sort_by(chunk, &compare));
+
+    // Queue entries are (next position, end of run); the smallest head wins.
+    let records = &*data;
+    let mut heads = PriorityQueue::new(|a: &(usize, usize), b: &(usize, usize)| {
+        compare(&records[b.0], &records[a.0]).then(b.0.cmp(&a.0))
+    });
+    for start in (0..records.len()).step_by(chunk_len) {
+        heads.push((start, (start + chunk_len).min(records.len())));
+    }
+
+    let mut order = Vec::with_capacity(records.len());
+    while let Some((next, end)) = heads.pop() {
+        order.push(next);
+        if next + 1 < end {
+            heads.push((next + 1, end));
+        }
+    }
+    apply_order(data, &order);
+}
+
+// Slices up to this length are finished with insertion sort.
+pub(crate) const INSERTION_SORT_THRESHOLD: usize = 20;
+// Slices up to this length are sorted on the current thread; spawning rayon
+// tasks for them costs more than it saves.
+pub(crate) const SEQUENTIAL_THRESHOLD: usize = 4096;
+// Slices longer than this take the ninther (median of three medians) as pivot.
+const NINTHER_THRESHOLD: usize = 128;
+
+/// Parallel introsort-style quick sort. The pivot is the median of three (or
+/// the ninther on large slices), partitioning is three-way so runs of equal
+/// keys are settled in one pass, small slices use insertion sort, and a slice
+/// that recurses deeper than `2 * log2(n)` falls back to heap sort, so the
+/// worst case stays O(n log n). Like `slice::sort_unstable_by`, equal elements
+/// may be reordered; see `stable_quick_sort_by`.
+pub fn quick_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let depth_limit = 2 * (usize::BITS - data.len().leading_zeros()) as usize;
+    quick_sort_par(data, &compare, depth_limit);
+}
+
+pub fn quick_sort_by_key<T, K, F>(data: &mut [T], key: F)
+where
+    T: Send,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    quick_sort_by(data, |a, b| key(a).cmp(&key(b)));
+}
+
+/// Stable variant of `quick_sort_by`: it sorts the record positions, breaking
+/// ties by original position, and then moves the records into place.
+pub fn stable_quick_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    stable_sort_with(data, compare, |order, compare| quick_sort_by(order, compare));
+}
+
+/// Makes any unstable sort stable: `sort` orders the record positions with a
+/// comparator that breaks ties by original position, and the records are then
+/// moved into place once.
+pub fn stable_sort_with<T, F, S>(data: &mut [T], compare: F, sort: S)
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    S: FnOnce(&mut [usize], &(dyn Fn(&usize, &usize) -> Ordering + Sync)),
+{
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    let records = &*data;
+    sort(&mut order, &|&a, &b| compare(&records[a], &records[b]).then(a.cmp(&b)));
+    apply_order(data, &order);
+}
+
+fn quick_sort_par<T, F>(mut data: &mut [T], compare: &F, mut depth_limit: usize)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    loop {
+        let len = data.len();
+        if len <= INSERTION_SORT_THRESHOLD {
+            insertion_sort(data, compare);
+            return;
+        }
+        if depth_limit == 0 {
+            heap_sort_by(data, compare);
+            return;
+        }
+        depth_limit -= 1;
+
+        let pivot_index = choose_pivot(data, compare);
+        let (lt, gt) = partition(data, pivot_index, compare);
+
+        // data[..lt] < pivot, data[lt..gt] == pivot, data[gt..] > pivot.
+        let (left, rest) = std::mem::take(&mut data).split_at_mut(lt);
+        let right = &mut rest[gt - lt..];
+
+        if len > SEQUENTIAL_THRESHOLD {
+            rayon::join(
+                || quick_sort_par(left, compare, depth_limit),
+                || quick_sort_par(right, compare, depth_limit),
+            );
+            return;
+        }
+
+        // Recurse into the smaller side and loop on the larger one, so the
+        // stack depth stays logarithmic.
+        if left.len() < right.len() {
+            quick_sort_par(left, compare, depth_limit);
+            data = right;
+        } else {
+            quick_sort_par(right, compare, depth_limit);
+            data = left;
+        }
+    }
+}
+
+pub(crate) fn choose_pivot<T, F>(data: &[T], compare: &F) -> usize
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let len = data.len();
+    let mid = len / 2;
+    if len <= NINTHER_THRESHOLD {
+        return median_of_three(data, 0, mid, len - 1, compare);
+    }
+
+    let step = len / 8;
+    let a = median_of_three(data, 0, step, 2 * step, compare);
+    let b = median_of_three(data, mid - step, mid, mid + step, compare);
+    let c = median_of_three(data, len - 1 - 2 * step, len - 1 - step, len - 1, compare);
+    median_of_three(data, a, b, c, compare)
+}
+
+fn median_of_three<T, F>(data: &[T], a: usize, b: usize, c: usize, compare: &F) -> usize
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let less = |i: usize, j: usize| compare(&data[i], &data[j]) == Ordering::Less;
+    if less(a, b) {
+        if less(b, c) {
+            b
+        } else if less(a, c) {
+            c
+        } else {
+            a
+        }
+    } else if less(a, c) {
+        a
+    } else if less(b, c) {
+        c
+    } else {
+        b
+    }
+}
+
+// Dijkstra's three-way partition around `data[pivot_index]`. Returns `(lt, gt)`
+// such that `data[lt..gt]` holds every element equal to the pivot.
+pub(crate) fn partition<T, F>(data: &mut [T], pivot_index: usize, compare: &F) -> (usize, usize)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    data.swap(0, pivot_index);
+
+    // data[lt] is always an element equal to the pivot.
+    let (mut lt, mut i, mut gt) = (0, 1, data.len());
+    while i < gt {
+        match compare(&data[i], &data[lt]) {
+            Ordering::Less => {
+                data.swap(lt, i);
+                lt += 1;
+                i += 1;
+            }
+            Ordering::Greater => {
+                gt -= 1;
+                data.swap(i, gt);
+            }
+            Ordering::Equal => i += 1,
+        }
+    }
+
+    (lt, gt)
+}
+
+pub(crate) fn insertion_sort<T, F>(data: &mut [T], compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    for i in 1..data.len() {
+        let mut j = i;
+        while j > 0 && compare(&data[j - 1], &data[j]) == Ordering::Greater {
+            data.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
@@ -0,0 +1,1333 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use crate::argsort::{
+    apply_permutation, counting_argsort_by_key, heap_argsort_by_key, in_order, merge_argsort_by, merge_argsort_by_key, quick_argsort_by_key,
+    radix_argsort_by_key,
+};
+use crate::avl::{Aggregate, AvlTree, Entry};
+use crate::date_tree::{DateTree, RecordFilter, SelectError, ValueSummary};
+use crate::benchmark::{
+    write_search_report, write_sort_report, ReportFormat, SearchBenchmark, SortAlgorithm, SortBenchmark, SortInput, Workload,
+};
+use crate::external::ExternalSort;
+use crate::order::{Nulls, OrderKey, OrderSpec};
+use crate::heap::{top_k_by, top_k_by_key, PriorityQueue};
+use crate::search::{
+    bis_by_key, bis_date, bounds_by_key, date_bounds, equal_range_around, equal_range_by_key, interpolation_search_by_key,
+    lower_bound_by_key, range_by_key, records_between, records_on, search_by_key, search_date, upper_bound_by_key, Binary,
+    Bis, Exponential, Interpolation, SearchStats, SEARCHES,
+};
+use crate::select::{bottom_k, median, nth_element, percentile, select_nth_by, top_k};
+use crate::sort::{
+    counting_sort_by_key, days_to_key, heap_sort_by, heap_sort_by_key, heap_sort_with_arity, par_heap_sort_by, merge_sort_by, merge_sort_by_key, quick_sort_by, quick_sort_by_key, radix_sort_by_days,
+    radix_sort_by_key, radix_sort_u64, stable_quick_sort_by,
+};
+use crate::{date_to_days, read_data, save_to_file, write_data, Column, Data, DateError, ErrorPolicy, LoadError, Loader, TradeDate, Weekday};
+
+const BAD_ROWS: &str = "\
+Direction,Year,Date,Weekday,Country,Commodity,Transport_Mode,Measure,Value,Cumulative
+Exports,2015,01/01/2015,Thursday,All,All,All,$,57000000,57000000
+Exports,2015,02/01/2015,Friday,All,All,All,$,n/a,110000000
+Exports,2015,03/01/2015,Saturday,All,All,All,$,6000000
+Exports,2015,04/01/2015,Sunday,All,All,All,$,24000000,140000000
+";
+
+#[test]
+fn test_date_to_days() {
+    assert_eq!(date_to_days("01/01/1970"), Some(0));
+    assert_eq!(date_to_days("01/01/2020"), Some(18262));
+    assert_eq!(date_to_days("31/12/2020"), Some(18627));
+    assert_eq!(date_to_days("invalid date"), None);
+}
+
+#[test]
+fn test_trade_date_month_boundaries_and_leap_years() {
+    let jan_31 = TradeDate::parse("31/01/2020").unwrap();
+    let feb_01 = TradeDate::parse("01/02/2020").unwrap();
+    assert!(jan_31 < feb_01);
+    assert_eq!(feb_01.days() - jan_31.days(), 1);
+
+    let feb_28 = TradeDate::parse("28/02/2020").unwrap();
+    let mar_01 = TradeDate::parse("01/03/2020").unwrap();
+    assert_eq!(mar_01.days() - feb_28.days(), 2);
+    assert!(TradeDate::parse("29/02/2020").is_ok());
+    assert!(TradeDate::parse("29/02/2019").is_err());
+    assert!(TradeDate::parse("29/02/2000").is_ok());
+    assert!(TradeDate::parse("29/02/1900").is_err());
+}
+
+#[test]
+fn test_trade_date_round_trip() {
+    for days in [-719_468, -1, 0, 16436, 18262, 18627, 2_932_896] {
+        let date = TradeDate::from_days(days);
+        assert_eq!(TradeDate::parse(&date.to_string()), Ok(date));
+    }
+
+    let date = TradeDate::from_ymd(2015, 1, 2).unwrap();
+    assert_eq!((date.day(), date.month(), date.year()), (2, 1, 2015));
+    assert_eq!(date.to_string(), "02/01/2015");
+    assert_eq!("2/1/2015".parse::<TradeDate>(), Ok(date));
+}
+
+#[test]
+fn test_trade_date_validation() {
+    assert_eq!(TradeDate::parse("01/13/2020"), Err(DateError::Month(13)));
+    assert_eq!(TradeDate::parse("31/04/2020"), Err(DateError::Day { day: 31, month: 4, year: 2020 }));
+    assert_eq!(TradeDate::parse("00/01/2020"), Err(DateError::Day { day: 0, month: 1, year: 2020 }));
+    assert!(matches!(TradeDate::parse("2020-01-01"), Err(DateError::Format(_))));
+    assert!(matches!(TradeDate::parse("01/01/2020/1"), Err(DateError::Format(_))));
+}
+
+#[test]
+fn test_trade_date_extreme_years() {
+    assert_eq!(TradeDate::parse("01/01/2147483647"), Err(DateError::OutOfRange(i32::MAX)));
+    assert_eq!(TradeDate::parse("01/01/-2147483648"), Err(DateError::OutOfRange(i32::MIN)));
+    assert_eq!(TradeDate::parse("01/01/10000"), Err(DateError::OutOfRange(10_000)));
+    assert_eq!(TradeDate::parse("31/12/9999").unwrap().to_string(), "31/12/9999");
+    assert_eq!(TradeDate::parse("01/01/0000").unwrap().year(), 0);
+
+    let last = TradeDate::from_days(i32::MAX);
+    let first = TradeDate::from_days(i32::MIN);
+    assert!(first.year() < 0 && last.year() > 9999);
+}
+
+#[test]
+fn test_trade_date_weekday() {
+    assert_eq!(TradeDate::parse("01/01/1970").unwrap().weekday(), Weekday::Thursday);
+    assert_eq!(TradeDate::parse("31/12/1969").unwrap().weekday(), Weekday::Wednesday);
+
+    // Every row of the sample export carries the weekday the portal computed.
+    for d in read_data("test.csv").unwrap() {
+        assert_eq!(d.trade_date().unwrap().weekday().name(), d.weekday);
+    }
+}
+
+#[test]
+fn test_read_data_from_csv() {
+    let data = read_data("test.csv").unwrap();
+    assert_eq!(data.len(), 18);
+    assert_eq!(data[0].date, "01/01/2015");
+    assert_eq!(data[0].comodity, "Milk powder, butter, and cheese");
+    assert_eq!(data[17].cumulative, 651000000);
+}
+
+#[test]
+fn test_read_data_missing_file() {
+    let error = read_data("does_not_exist.csv").unwrap_err();
+    assert!(matches!(error, LoadError::Io { .. }));
+    assert!(error.to_string().starts_with("does_not_exist.csv: "));
+}
+
+#[test]
+fn test_load_fail_fast_reports_position() {
+    let error = Loader::new().load_from_reader(BAD_ROWS.as_bytes(), "bad").unwrap_err();
+    match error {
+        LoadError::Field { at, column, name, ref value, .. } => {
+            assert_eq!((at.row, at.line), (2, 3));
+            assert_eq!(at.byte, BAD_ROWS.find("Exports,2015,02/01").unwrap() as u64);
+            assert_eq!((column, name, value.as_str()), (8, "Value", "n/a"));
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_load_skip_collects_rejected_rows() {
+    let report = Loader::new()
+        .policy(ErrorPolicy::Skip)
+        .load_from_reader(BAD_ROWS.as_bytes(), "bad")
+        .unwrap();
+
+    let dates: Vec<&str> = report.data.iter().map(|d| d.date.as_str()).collect();
+    assert_eq!(dates, ["01/01/2015", "04/01/2015"]);
+    assert_eq!(report.rejected.len(), 2);
+    assert!(matches!(report.rejected[0], LoadError::Field { column: 8, .. }));
+    assert!(matches!(report.rejected[1], LoadError::Malformed { .. }));
+    assert_eq!(report.rejected[1].position().unwrap().line, 4);
+    assert!(report.coerced.is_empty());
+    assert!(report.summary().starts_with("2 records loaded, 2 rows rejected, 0 fields coerced"));
+}
+
+#[test]
+fn test_load_coerce_keeps_rows() {
+    let report = Loader::new()
+        .policy(ErrorPolicy::Coerce)
+        .load_from_reader(BAD_ROWS.as_bytes(), "bad")
+        .unwrap();
+
+    assert_eq!(report.data.len(), 3);
+    assert_eq!(report.data[1].value, 0);
+    assert_eq!(report.data[1].cumulative, 110000000);
+    assert_eq!(report.coerced.len(), 1);
+    // A short row cannot be coerced into a record.
+    assert_eq!(report.rejected.len(), 1);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn test_load_rejects_bad_dates() {
+    let csv = "\
+Direction,Year,Date,Weekday,Country,Commodity,Transport_Mode,Measure,Value,Cumulative
+Exports,2015,01/01/2015,Thursday,All,All,All,$,57000000,57000000
+Exports,2015,31/02/2015,Friday,All,All,All,$,n/a,110000000
+Exports,2015,2015-01-03,Saturday,All,All,All,$,6000000,116000000
+";
+    let error = Loader::new().load_from_reader(csv.as_bytes(), "dates").unwrap_err();
+    match error {
+        LoadError::Field { at, column, name, ref value, ref message } => {
+            assert_eq!(at.line, 3);
+            assert_eq!((column, name, value.as_str()), (2, "Date", "31/02/2015"));
+            assert_eq!(message, "invalid day 31 for 02/2015");
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+
+    // A date cannot be coerced like a number: the rows are rejected.
+    for policy in [ErrorPolicy::Skip, ErrorPolicy::Coerce] {
+        let report = Loader::new().policy(policy).load_from_reader(csv.as_bytes(), "dates").unwrap();
+        assert_eq!(report.data.len(), 1);
+        assert_eq!(report.rejected.len(), 2);
+        assert!(report.coerced.is_empty());
+        assert!(matches!(report.rejected[1], LoadError::Field { column: 2, .. }));
+    }
+}
+
+#[test]
+fn test_load_maps_columns_by_header() {
+    let csv = "\
+Value,Date,Source,Cumulative,Direction,Year,Weekday,Country,Commodity,Transport_Mode,Measure
+57000000,01/01/2015,portal,57000000,Exports,2015,Thursday,All,Logs,Sea,$
+";
+    let data = Loader::new().load_from_reader(csv.as_bytes(), "reordered").unwrap().data;
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].value, 57000000);
+    assert_eq!(data[0].date, "01/01/2015");
+    assert_eq!(data[0].direction, "Exports");
+    assert_eq!(data[0].comodity, "Logs");
+    assert_eq!(data[0].transport_mode, "Sea");
+}
+
+#[test]
+fn test_load_reports_missing_columns() {
+    let csv = "Direction,Year,Date,Weekday,Country,Measure,Value\n";
+    match Loader::new().load_from_reader(csv.as_bytes(), "short") {
+        Err(LoadError::MissingColumns(names)) => {
+            assert_eq!(names, ["Commodity", "Transport_Mode", "Cumulative"]);
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_load_with_header_aliases() {
+    let csv = "\
+direction,year,date,weekday,country,Commodity_Name,transport mode,measure,value,Cumulative_Value
+Exports,2015,01/01/2015,Thursday,All,Logs,Sea,$,5,5
+";
+    let loader = Loader::new()
+        .alias("Commodity_Name", Column::Commodity)
+        .alias("Transport Mode", Column::TransportMode)
+        .alias("Cumulative_Value", Column::Cumulative);
+    let data = loader.load_from_reader(csv.as_bytes(), "renamed").unwrap().data;
+    assert_eq!(data[0].comodity, "Logs");
+    assert_eq!(data[0].transport_mode, "Sea");
+    assert_eq!(data[0].cumulative, 5);
+}
+
+#[test]
+fn test_save_to_file_round_trip() {
+    let data = read_data("test.csv").unwrap();
+    let path = std::env::temp_dir().join("trade_core_round_trip.csv");
+    save_to_file(&data, path.to_str().unwrap()).unwrap();
+
+    let reloaded = read_data(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(reloaded, data);
+}
+
+// Records whose text fields hold commas, quotes and line breaks.
+fn records_needing_quotes() -> Vec<Data> {
+    let mut records = sample_records();
+    records[0].comodity = "Milk \"powder\", butter".to_string();
+    records[1].country = "\"Quoted\"".to_string();
+    records[2].transport_mode = "Sea,\nthen air\r\n".to_string();
+    records[3].measure = "\"".to_string();
+    records
+}
+
+#[test]
+fn test_write_data_quotes_fields() {
+    let records = records_needing_quotes();
+    let mut csv = Vec::new();
+    write_data(&records, &mut csv).unwrap();
+    assert!(String::from_utf8_lossy(&csv).contains(",\"Milk \"\"powder\"\", butter\","));
+    assert_eq!(Loader::new().load_from_reader(csv.as_slice(), "quoted").unwrap().data, records);
+
+    let path = std::env::temp_dir().join("trade_core_quoted_round_trip.csv");
+    save_to_file(&records, path.to_str().unwrap()).unwrap();
+    let reloaded = read_data(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(reloaded, records);
+}
+
+#[test]
+fn test_display() {
+    let data = Data {
+        direction: "Exports".to_string(),
+        year: 2015,
+        date: "01/01/2015".to_string(),
+        value: 7,
+        ..Data::new()
+    };
+    assert_eq!(data.to_string(), "Exports, 2015, 01/01/2015, , , , , , 7, 0");
+}
+
+#[test]
+fn test_error_policy_from_str() {
+    assert_eq!("fail-fast".parse::<ErrorPolicy>(), Ok(ErrorPolicy::FailFast));
+    assert_eq!("skip".parse::<ErrorPolicy>(), Ok(ErrorPolicy::Skip));
+    assert_eq!("coerce".parse::<ErrorPolicy>(), Ok(ErrorPolicy::Coerce));
+    assert!("ignore".parse::<ErrorPolicy>().is_err());
+}
+
+fn sample_records() -> Vec<Data> {
+    read_data("test.csv").unwrap()
+}
+
+fn unsorted_numbers() -> Vec<u64> {
+    vec![5, 3, 9, 3, 0, 12, 7, 7, 1, 5, 3, 8]
+}
+
+#[test]
+fn test_sorts_by_key_match_std() {
+    let mut expected = unsorted_numbers();
+    expected.sort();
+
+    let mut counting = unsorted_numbers();
+    counting_sort_by_key(&mut counting, |&n| n);
+    assert_eq!(counting, expected);
+
+    let mut merge = unsorted_numbers();
+    merge_sort_by_key(&mut merge, |&n| n);
+    assert_eq!(merge, expected);
+
+    let mut heap = unsorted_numbers();
+    heap_sort_by_key(&mut heap, |&n| n);
+    assert_eq!(heap, expected);
+
+    let mut quick = unsorted_numbers();
+    quick_sort_by_key(&mut quick, |&n| n);
+    assert_eq!(quick, expected);
+}
+
+#[test]
+fn test_sorts_by_comparator_descending() {
+    let mut expected = unsorted_numbers();
+    expected.sort_by(|a, b| b.cmp(a));
+
+    let mut merge = unsorted_numbers();
+    merge_sort_by(&mut merge, |a, b| b.cmp(a));
+    assert_eq!(merge, expected);
+
+    let mut heap = unsorted_numbers();
+    heap_sort_by(&mut heap, |a, b| b.cmp(a));
+    assert_eq!(heap, expected);
+
+    let mut quick = unsorted_numbers();
+    quick_sort_by(&mut quick, |a, b| b.cmp(a));
+    assert_eq!(quick, expected);
+}
+
+#[test]
+fn test_sort_records_by_composite_key() {
+    let mut expected = sample_records();
+    expected.sort_by(|a, b| a.comodity.cmp(&b.comodity).then(b.value.cmp(&a.value)));
+
+    let mut merge = sample_records();
+    merge_sort_by(&mut merge, |a, b| a.comodity.cmp(&b.comodity).then(b.value.cmp(&a.value)));
+    assert_eq!(merge, expected);
+
+    let mut quick = sample_records();
+    quick_sort_by_key(&mut quick, |d| (d.comodity.clone(), std::cmp::Reverse(d.value)));
+    let keys = |data: &[Data]| data.iter().map(|d| (d.comodity.clone(), d.value)).collect::<Vec<_>>();
+    assert_eq!(keys(&quick), keys(&expected));
+}
+
+#[test]
+fn test_stable_sorts_keep_input_order() {
+    // Every sample record is from 2015, so a stable sort on the year is a no-op.
+    let mut merge = sample_records();
+    merge_sort_by_key(&mut merge, |d| d.year);
+    assert_eq!(merge, sample_records());
+
+    let mut counting = sample_records();
+    counting_sort_by_key(&mut counting, |d| d.year as u64);
+    assert_eq!(counting, sample_records());
+}
+
+#[test]
+fn test_sorts_handle_empty_and_single() {
+    let mut empty: Vec<u64> = Vec::new();
+    counting_sort_by_key(&mut empty, |&n| n);
+    merge_sort_by_key(&mut empty, |&n| n);
+    heap_sort_by_key(&mut empty, |&n| n);
+    quick_sort_by_key(&mut empty, |&n| n);
+    assert!(empty.is_empty());
+
+    let mut single = vec![4u64];
+    quick_sort_by_key(&mut single, |&n| n);
+    assert_eq!(single, [4]);
+}
+
+#[test]
+fn test_counting_sort_compresses_wide_ranges() {
+    // A range of ~u64::MAX would need an impossible count vector without
+    // compressing the keys to their ranks.
+    let mut data = vec![(u64::MAX, 'a'), (7, 'b'), (1 << 40, 'c'), (7, 'd'), (0, 'e'), (u64::MAX, 'f')];
+    counting_sort_by_key(&mut data, |&(k, _)| k);
+    assert_eq!(data, [(0, 'e'), (7, 'b'), (7, 'd'), (1 << 40, 'c'), (u64::MAX, 'a'), (u64::MAX, 'f')]);
+
+    let mut records = sample_records();
+    let mut expected = sample_records();
+    expected.sort_by_key(|d| d.value);
+    counting_sort_by_key(&mut records, |d| d.value);
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn test_radix_sort_matches_std_and_is_stable() {
+    let mut numbers: Vec<u64> = (0..1000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (i % 64)).collect();
+    let mut expected = numbers.clone();
+    expected.sort();
+    radix_sort_u64(&mut numbers);
+    assert_eq!(numbers, expected);
+
+    let mut records = sample_records();
+    let mut expected = sample_records();
+    expected.sort_by_key(|d| d.cumulative % 1000);
+    radix_sort_by_key(&mut records, |d| d.cumulative % 1000);
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn test_radix_sort_by_days() {
+    assert!(days_to_key(-1) < days_to_key(0));
+    assert!(days_to_key(i32::MIN) < days_to_key(i32::MAX));
+
+    let mut days = vec![18_262, -5, 0, i32::MAX, -719_468, 3, i32::MIN, 18_262];
+    let mut expected = days.clone();
+    expected.sort();
+    radix_sort_by_days(&mut days, |&d| d);
+    assert_eq!(days, expected);
+
+    let mut records = sample_records();
+    records.reverse();
+    radix_sort_by_days(&mut records, |d| d.trade_date().unwrap().days());
+    assert_eq!(records, sample_records());
+}
+
+// Inputs that push a naive middle-pivot quick sort towards O(n²) or deep
+// recursion: presorted, reversed, all equal, few distinct keys, organ pipe.
+fn quick_sort_workloads(n: u64) -> Vec<Vec<u64>> {
+    vec![
+        (0..n).collect(),
+        (0..n).rev().collect(),
+        vec![42; n as usize],
+        (0..n).map(|i| i % 3).collect(),
+        (0..n).map(|i| if i < n / 2 { i } else { n - i }).collect(),
+        (0..n).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % 1000).collect(),
+    ]
+}
+
+#[test]
+fn test_quick_sort_adversarial_inputs() {
+    for n in [0, 1, 2, 19, 21, 200, 100_000] {
+        for mut numbers in quick_sort_workloads(n) {
+            let mut expected = numbers.clone();
+            expected.sort();
+            quick_sort_by_key(&mut numbers, |&k| k);
+            assert_eq!(numbers, expected, "n = {}", n);
+        }
+    }
+}
+
+#[test]
+fn test_parallel_merge_sort_is_stable() {
+    // Long enough for the parallel split and the binary-search merge.
+    for n in [21, 5000, 100_000] {
+        for numbers in quick_sort_workloads(n) {
+            let mut pairs: Vec<(u64, usize)> = numbers.into_iter().enumerate().map(|(i, k)| (k % 97, i)).collect();
+            let mut expected = pairs.clone();
+            expected.sort_by_key(|&(k, _)| k);
+            merge_sort_by(&mut pairs, |a, b| a.0.cmp(&b.0));
+            assert_eq!(pairs, expected, "n = {}", n);
+        }
+    }
+}
+
+#[test]
+fn test_stable_quick_sort_keeps_equal_keys_in_order() {
+    let mut pairs: Vec<(u64, usize)> = (0..10_000).map(|i| ((i as u64 * 7919) % 13, i)).collect();
+    let mut expected = pairs.clone();
+    expected.sort_by_key(|&(k, _)| k);
+    stable_quick_sort_by(&mut pairs, |a, b| a.0.cmp(&b.0));
+    assert_eq!(pairs, expected);
+
+    let mut records = sample_records();
+    records.reverse();
+    let mut expected = records.clone();
+    expected.sort_by_key(|d| d.year);
+    stable_quick_sort_by(&mut records, |a, b| a.year.cmp(&b.year));
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn test_priority_queue_push_pop_peek() {
+    for arity in [2, 3, 4, 8] {
+        let mut queue = PriorityQueue::with_arity(arity, |a: &u64, b: &u64| a.cmp(b));
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        for n in unsorted_numbers() {
+            queue.push(n);
+        }
+        assert_eq!(queue.len(), unsorted_numbers().len());
+        assert_eq!(queue.peek(), Some(&12));
+
+        let mut popped = Vec::new();
+        while let Some(n) = queue.pop() {
+            popped.push(n);
+        }
+        let mut expected = unsorted_numbers();
+        expected.sort_by(|a, b| b.cmp(a));
+        assert_eq!(popped, expected, "arity {}", arity);
+    }
+}
+
+#[test]
+fn test_priority_queue_from_vec_and_sorted_vec() {
+    let mut expected = unsorted_numbers();
+    expected.sort();
+
+    let queue = PriorityQueue::from_vec(unsorted_numbers(), 3, |a: &u64, b: &u64| a.cmp(b));
+    assert_eq!(queue.peek(), Some(&12));
+    assert_eq!(queue.into_sorted_vec(), expected);
+
+    // Records by cumulative value: the queue works on `Data` with any key.
+    let mut queue = PriorityQueue::new(|a: &Data, b: &Data| a.cumulative.cmp(&b.cumulative));
+    for record in sample_records() {
+        queue.push(record);
+    }
+    let largest = sample_records().into_iter().max_by_key(|d| d.cumulative).unwrap();
+    assert_eq!(queue.pop(), Some(largest));
+}
+
+#[test]
+fn test_top_k() {
+    assert_eq!(top_k_by(unsorted_numbers(), 3, |a, b| a.cmp(b)), [12, 9, 8]);
+    assert_eq!(top_k_by(unsorted_numbers(), 3, |a, b| b.cmp(a)), [0, 1, 3]);
+    assert!(top_k_by(unsorted_numbers(), 0, |a, b| a.cmp(b)).is_empty());
+    assert_eq!(top_k_by(vec![2u64, 1], 5, |a, b| a.cmp(b)), [2, 1]);
+
+    let mut expected = sample_records();
+    expected.sort_by_key(|d| std::cmp::Reverse(d.value));
+    let top = top_k_by_key(sample_records(), 4, |d| d.value);
+    let values: Vec<u64> = top.iter().map(|d| d.value).collect();
+    assert_eq!(values, expected[..4].iter().map(|d| d.value).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_heap_sort_variants() {
+    for n in [0, 1, 2, 19, 200, 20_000] {
+        for numbers in quick_sort_workloads(n) {
+            let mut expected = numbers.clone();
+            expected.sort();
+
+            for arity in [2, 3, 4] {
+                let mut sorted = numbers.clone();
+                heap_sort_with_arity(&mut sorted, arity, |a, b| a.cmp(b));
+                assert_eq!(sorted, expected, "n = {}, arity {}", n, arity);
+            }
+
+            let mut sorted = numbers.clone();
+            par_heap_sort_by(&mut sorted, |a, b| a.cmp(b));
+            assert_eq!(sorted, expected, "n = {}", n);
+        }
+    }
+}
+
+#[test]
+fn test_select_nth_matches_sorted_position() {
+    for n in [1, 2, 19, 200, 20_000] {
+        for numbers in quick_sort_workloads(n) {
+            let mut expected = numbers.clone();
+            expected.sort();
+            for index in [0, n as usize / 3, n as usize - 1] {
+                let mut data = numbers.clone();
+                let nth = *select_nth_by(&mut data, index, |a, b| a.cmp(b));
+                assert_eq!(nth, expected[index], "n = {}, index {}", n, index);
+                assert!(data[..index].iter().all(|&x| x <= nth));
+                assert!(data[index + 1..].iter().all(|&x| x >= nth));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_top_and_bottom_k() {
+    let numbers: Vec<u64> = (0..50_000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % 10_000).collect();
+    let mut sorted = numbers.clone();
+    sorted.sort();
+
+    let top = top_k(&numbers, 25, |&n| n);
+    let expected: Vec<u64> = sorted.iter().rev().take(25).copied().collect();
+    assert_eq!(top, expected);
+    assert_eq!(bottom_k(&numbers, 25, |&n| n), sorted[..25]);
+    assert_eq!(top_k(&numbers, 0, |&n| n), []);
+    assert_eq!(bottom_k(&[3u64, 1, 2], 10, |&n| n), [1, 2, 3]);
+
+    // Ties keep input order, so equal-value records come out as in the file.
+    let records = sample_records();
+    let top = top_k(&records, 3, |d| d.year);
+    assert_eq!(top, records[..3]);
+    let largest = records.iter().map(|d| d.cumulative).max().unwrap();
+    assert_eq!(top_k(&records, 1, |d| d.cumulative)[0].cumulative, largest);
+}
+
+#[test]
+fn test_median_and_percentile() {
+    let numbers = unsorted_numbers();
+    let mut sorted = numbers.clone();
+    sorted.sort();
+
+    assert_eq!(nth_element(&numbers, 4, |&n| n), Some(sorted[4]));
+    assert_eq!(nth_element(&numbers, numbers.len(), |&n| n), None);
+    assert_eq!(median(&numbers, |&n| n), Some(sorted[(numbers.len() - 1) / 2]));
+    assert_eq!(median(&[] as &[u64], |&n| n), None);
+
+    assert_eq!(percentile(&numbers, 0.0, |&n| n), Some(0));
+    assert_eq!(percentile(&numbers, 100.0, |&n| n), Some(12));
+    assert_eq!(percentile(&numbers, 50.0, |&n| n), median(&numbers, |&n| n));
+    // Nearest rank: 25% of 12 records is rank 3.
+    assert_eq!(percentile(&numbers, 25.0, |&n| n), Some(sorted[2]));
+    assert_eq!(percentile(&numbers, 100.5, |&n| n), None);
+    assert_eq!(percentile(&numbers, f64::NAN, |&n| n), None);
+
+    let records = sample_records();
+    let mut values: Vec<u64> = records.iter().map(|d| d.value).collect();
+    values.sort();
+    assert_eq!(median(&records, |d| d.value).unwrap().value, values[(values.len() - 1) / 2]);
+}
+
+#[test]
+fn test_selection_on_large_input() {
+    // Long enough for the parallel narrowing, with many repeated keys.
+    let numbers: Vec<u64> = (0..50_000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % 1_000).collect();
+    let mut sorted = numbers.clone();
+    sorted.sort();
+    for n in [0, 1, 4_095, 25_000, 49_999] {
+        assert_eq!(nth_element(&numbers, n, |&x| x), Some(sorted[n]));
+    }
+    assert_eq!(median(&numbers, |&x| x), Some(sorted[24_999]));
+    assert_eq!(percentile(&numbers, 99.0, |&x| x), Some(sorted[49_499]));
+    // Already sorted input with distinct keys.
+    assert_eq!(nth_element(&sorted, 12_345, |&x| x), Some(sorted[12_345]));
+}
+
+#[test]
+fn test_external_sort_matches_in_memory_sort() {
+    let records = sample_records();
+    let mut csv = Vec::new();
+    write_data(&records, &mut csv).unwrap();
+    let by_value = |a: &Data, b: &Data| a.value.cmp(&b.value);
+
+    let mut expected = records.clone();
+    merge_sort_by(&mut expected, by_value);
+
+    // Runs of 2 merged 3 at a time: 9 runs, two merge passes.
+    let sorter = ExternalSort::new().run_len(2).fan_in(3);
+    let mut out = Vec::new();
+    let report = sorter.sort_records(Loader::new().records_from_reader(csv.as_slice(), "sample").unwrap(), &mut out, by_value).unwrap();
+    assert_eq!((report.records, report.runs, report.merge_passes), (18, 9, 2));
+    assert_eq!(Loader::new().load_from_reader(out.as_slice(), "sorted").unwrap().data, expected);
+
+    // A single run never touches the disk.
+    let mut out = Vec::new();
+    let report = ExternalSort::new().sort_records(Loader::new().records_from_reader(csv.as_slice(), "sample").unwrap(), &mut out, by_value).unwrap();
+    assert_eq!((report.runs, report.merge_passes), (0, 0));
+    assert_eq!(Loader::new().load_from_reader(out.as_slice(), "sorted").unwrap().data, expected);
+}
+
+#[test]
+fn test_external_sort_keeps_quoted_fields() {
+    let records = records_needing_quotes();
+    let dir = tempfile::tempdir().unwrap();
+    let (input, output) = (dir.path().join("in.csv"), dir.path().join("out.csv"));
+    save_to_file(&records, input.to_str().unwrap()).unwrap();
+
+    // One record per run, so every record goes through a spilled run.
+    let by_value = |a: &Data, b: &Data| a.value.cmp(&b.value);
+    let report = ExternalSort::new().run_len(1).fan_in(4).sort_file(input.to_str().unwrap(), output.to_str().unwrap(), by_value).unwrap();
+    assert_eq!(report.runs, records.len());
+
+    let mut expected = records;
+    merge_sort_by(&mut expected, by_value);
+    assert_eq!(read_data(output.to_str().unwrap()).unwrap(), expected);
+}
+
+#[test]
+fn test_external_sort_keeps_backslashes() {
+    // `write_data` quotes RFC 4180 style, so a backslash is an ordinary
+    // character and must come back unchanged, also through the spilled runs.
+    let mut records = sample_records();
+    records[0].country = "C:\\x,y".to_string();
+    records[1].comodity = "ends with \\".to_string();
+    records[2].measure = "\\\"".to_string();
+    let dir = tempfile::tempdir().unwrap();
+    let (input, output) = (dir.path().join("in.csv"), dir.path().join("out.csv"));
+    save_to_file(&records, input.to_str().unwrap()).unwrap();
+    assert_eq!(read_data(input.to_str().unwrap()).unwrap(), records);
+
+    let by_value = |a: &Data, b: &Data| a.value.cmp(&b.value);
+    ExternalSort::new().run_len(1).fan_in(4).sort_file(input.to_str().unwrap(), output.to_str().unwrap(), by_value).unwrap();
+
+    let mut expected = records;
+    merge_sort_by(&mut expected, by_value);
+    assert_eq!(read_data(output.to_str().unwrap()).unwrap(), expected);
+}
+
+#[test]
+fn test_external_sort_error_policies() {
+    let by_value = |a: &Data, b: &Data| a.value.cmp(&b.value);
+
+    let records = Loader::new().records_from_reader(BAD_ROWS.as_bytes(), "bad").unwrap();
+    assert!(ExternalSort::new().run_len(1).sort_records(records, Vec::new(), by_value).is_err());
+
+    let loader = Loader::new().policy(ErrorPolicy::Skip);
+    let mut out = Vec::new();
+    let report = ExternalSort::new()
+        .run_len(1)
+        .sort_records(loader.records_from_reader(BAD_ROWS.as_bytes(), "bad").unwrap(), &mut out, by_value)
+        .unwrap();
+    assert_eq!((report.records, report.rejected.len()), (2, 2));
+    let values: Vec<u64> = Loader::new().load_from_reader(out.as_slice(), "sorted").unwrap().data.iter().map(|d| d.value).collect();
+    assert_eq!(values, [24000000, 57000000]);
+}
+
+#[test]
+fn test_argsorts_agree_with_stable_sort() {
+    let records = sample_records();
+    let mut expected = records.clone();
+    expected.sort_by_key(|d| d.value);
+
+    let orders = [
+        merge_argsort_by_key(&records, |d| d.value),
+        quick_argsort_by_key(&records, |d| d.value),
+        heap_argsort_by_key(&records, |d| d.value),
+        counting_argsort_by_key(&records, |d| d.value),
+        radix_argsort_by_key(&records, |d| d.value),
+    ];
+    for order in &orders {
+        let sorted: Vec<Data> = in_order(&records, order).cloned().collect();
+        assert_eq!(sorted, expected);
+    }
+
+    // Several orderings of the same records side by side.
+    let by_country = merge_argsort_by(&records, |a, b| a.country.cmp(&b.country));
+    let countries: Vec<&str> = in_order(&records, &by_country).map(|d| d.country.as_str()).collect();
+    assert!(countries.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(in_order(&records, &orders[0]).next_back(), expected.last());
+
+    let mut moved = records.clone();
+    apply_permutation(&mut moved, &orders[0]);
+    assert_eq!(moved, expected);
+}
+
+#[test]
+#[should_panic(expected = "appears twice")]
+fn test_apply_permutation_rejects_duplicates() {
+    apply_permutation(&mut [1, 2, 3], &[0, 2, 0]);
+}
+
+#[test]
+fn test_order_spec_parse_and_display() {
+    let spec: OrderSpec = "country, Date:DESC ,value:nulls-first".parse().unwrap();
+    assert_eq!(
+        spec.keys(),
+        [
+            OrderKey::ascending(Column::Country),
+            OrderKey::descending(Column::Date),
+            OrderKey::ascending(Column::Value).nulls(Nulls::First),
+        ]
+    );
+    assert_eq!(spec.to_string(), "country,date:desc,value:nulls-first");
+    assert_eq!(spec.to_string().parse::<OrderSpec>().unwrap(), spec);
+    assert_eq!("transport-mode".parse::<OrderKey>().unwrap().column, Column::TransportMode);
+
+    assert!("".parse::<OrderSpec>().is_err());
+    assert!("country,,value".parse::<OrderSpec>().is_err());
+    assert!("weekday:sideways".parse::<OrderSpec>().unwrap_err().contains("sideways"));
+    assert!("colour".parse::<OrderSpec>().unwrap_err().contains("colour"));
+}
+
+#[test]
+fn test_order_spec_compare_and_integer_passes() {
+    let mut records = sample_records();
+    records[3].date = "not a date".to_string();
+    records[5].country = String::new();
+
+    let spec: OrderSpec = "country:desc,date:desc:nulls-first,value".parse().unwrap();
+    let mut sorted = records.clone();
+    merge_sort_by(&mut sorted, |a, b| spec.compare(a, b));
+    assert!(sorted.last().unwrap().country.is_empty());
+    for pair in sorted.windows(2) {
+        assert_ne!(spec.compare(&pair[0], &pair[1]), Ordering::Greater);
+    }
+    assert!("country".parse::<OrderSpec>().unwrap().integer_passes().is_none());
+
+    // The integer passes of an all-numeric spec give the comparator's order.
+    for text in ["year:desc,date:nulls-first,value", "date:desc,cumulative:desc", "date:nulls-last"] {
+        let spec: OrderSpec = text.parse().unwrap();
+        let mut expected = records.clone();
+        merge_sort_by(&mut expected, |a, b| spec.compare(a, b));
+        let mut radix = records.clone();
+        for pass in spec.integer_passes().unwrap() {
+            radix_sort_by_key(&mut radix, pass);
+        }
+        assert_eq!(radix, expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_bound_searches() {
+    let mut numbers = unsorted_numbers();
+    numbers.sort();
+    // [0, 1, 3, 3, 3, 5, 5, 7, 7, 8, 9, 12]
+    assert_eq!(lower_bound_by_key(&numbers, &3, |&n| n), 2);
+    assert_eq!(upper_bound_by_key(&numbers, &3, |&n| n), 5);
+    assert_eq!(equal_range_by_key(&numbers, &7, |&n| n), 7..9);
+    assert_eq!(equal_range_by_key(&numbers, &4, |&n| n), 5..5);
+    assert_eq!(equal_range_by_key(&numbers, &20, |&n| n), 12..12);
+    assert_eq!(equal_range_around(&numbers, 3, |&n| n), 2..5);
+    assert_eq!(range_by_key(&numbers, 3..=7, |&n| n), [3, 3, 3, 5, 5, 7, 7]);
+    assert_eq!(range_by_key(&numbers, 4..7, |&n| n), [5, 5]);
+    assert_eq!(bounds_by_key(&numbers, 8.., |&n| n), 9..12);
+    let (from, to) = (9, 2);
+    assert!(bounds_by_key(&numbers, from..=to, |&n| n).is_empty());
+}
+
+#[test]
+fn test_records_by_date() {
+    // Three rows per date, plus one unparsable date sorted first.
+    let mut records: Vec<Data> = sample_records().into_iter().flat_map(|d| [d.clone(), d.clone(), d]).collect();
+    records.insert(0, Data { date: "unknown".to_string(), ..Data::new() });
+    let date = |s: &str| TradeDate::parse(s).unwrap();
+
+    let day = records_on(&records, date("05/01/2015"));
+    assert_eq!(day.len(), 3);
+    assert!(day.iter().all(|d| d.date == "05/01/2015"));
+    assert!(records_on(&records, date("05/01/2016")).is_empty());
+
+    let week = records_between(&records, date("05/01/2015")..=date("11/01/2015"));
+    assert_eq!(week.len(), 21);
+    assert_eq!((week[0].date.as_str(), week[20].date.as_str()), ("05/01/2015", "11/01/2015"));
+    assert_eq!(date_bounds(&records, ..date("02/01/2015")), 1..4);
+    assert_eq!(records_between(&records, ..).len(), records.len() - 1);
+}
+
+#[test]
+fn test_interpolation_search_edge_cases() {
+    let search = |data: &[u64], key| interpolation_search_by_key(data, key, |&n| n);
+    assert_eq!(search(&[], 5), None);
+    assert_eq!(search(&[5], 5), Some(0));
+    assert_eq!(search(&[5], 4), None);
+    // Equal keys at both ends of the window used to divide by zero.
+    assert_eq!(search(&[7; 10], 7), Some(0));
+    assert_eq!(search(&[7; 10], 8), None);
+    // Keys below the range used to underflow, extreme keys to overflow.
+    let extremes = [0, 1, u64::MAX / 2, u64::MAX - 1, u64::MAX];
+    for (i, &key) in extremes.iter().enumerate() {
+        assert_eq!(search(&extremes, key), Some(i));
+    }
+    assert_eq!(search(&[3, 9], 0), None);
+    assert_eq!(search(&[3, 9], 10), None);
+
+    // Every key, present or not, on uniform, duplicated and skewed data.
+    let workloads: [Vec<u64>; 3] = [
+        (0..1000).map(|i| i * 3).collect(),
+        (0..1000).map(|i| i / 7 * 7).collect(),
+        (0..64).map(|i| 1u64 << i).collect(),
+    ];
+    for data in &workloads {
+        for key in data.iter().flat_map(|&k| [k.saturating_sub(1), k, k + 1]) {
+            match search(data, key) {
+                Some(i) => assert_eq!(data[i], key),
+                None => assert!(data.binary_search(&key).is_err(), "missed {}", key),
+            }
+        }
+    }
+}
+
+fn search_workloads() -> Vec<Vec<u64>> {
+    vec![
+        vec![],
+        vec![4],
+        vec![1, 5, 9],
+        vec![7; 50],
+        (0..10_000).map(|i| i * 3).collect(),
+        (0..10_000).map(|i| i / 7 * 7).collect(),
+        (0..64).map(|i| 1u64 << i).collect(),
+        (0..5_000).map(|i| i * i).collect(),
+        vec![0, 0, 1, u64::MAX - 1, u64::MAX, u64::MAX],
+    ]
+}
+
+#[test]
+fn test_every_search_matches_binary_search() {
+    for search in SEARCHES {
+        for data in &search_workloads() {
+            let keys = data.iter().flat_map(|&k| [k.saturating_sub(1), k, k.saturating_add(1)]).chain([0, u64::MAX]);
+            for key in keys {
+                match (search_by_key(search, data, key, |&n| n, &mut SearchStats::default()), data.binary_search(&key)) {
+                    (Ok(i), Ok(_)) => assert_eq!(data[i], key, "{}", search.name()),
+                    (Err(i), Err(j)) => assert_eq!(i, j, "{}: insertion point for {}", search.name(), key),
+                    (found, expected) => panic!("{}: key {}: {:?}, binary search {:?}", search.name(), key, found, expected),
+                }
+            }
+        }
+    }
+
+    let records = sample_records();
+    let date = TradeDate::parse("09/01/2015").unwrap();
+    for search in SEARCHES {
+        let index = search_date(search, &records, date, &mut SearchStats::default()).unwrap();
+        assert_eq!(records[index].date, "09/01/2015", "{}", search.name());
+    }
+}
+
+#[test]
+fn test_exponential_search_is_cheap_near_the_start() {
+    let data: Vec<u64> = (0..100_000).collect();
+    let (mut binary, mut exponential) = (SearchStats::default(), SearchStats::default());
+    for key in 0..16 {
+        assert_eq!(search_by_key(&Binary, &data, key, |&n| n, &mut binary), Ok(key as usize));
+        assert_eq!(search_by_key(&Exponential, &data, key, |&n| n, &mut exponential), Ok(key as usize));
+    }
+    assert!(exponential.probes * 2 < binary.probes, "{:?} vs {:?}", exponential, binary);
+}
+
+#[test]
+fn test_bis_matches_binary_search() {
+    let workloads = search_workloads();
+    for data in &workloads {
+        let keys = data.iter().flat_map(|&k| [k.saturating_sub(1), k, k.saturating_add(1)]).chain([0, u64::MAX]);
+        for key in keys {
+            match (bis_by_key(data, key, |&n| n), data.binary_search(&key)) {
+                (Ok(i), Ok(_)) => assert_eq!(data[i], key),
+                (Err(i), Err(j)) => assert_eq!(i, j, "insertion point for {}", key),
+                (found, expected) => panic!("key {}: bis {:?}, binary search {:?}", key, found, expected),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_search_stats() {
+    let data: Vec<u64> = (0..100_000).map(|i| i * 10).collect();
+    let (mut binary, mut interpolation, mut bis) = (SearchStats::default(), SearchStats::default(), SearchStats::default());
+    for key in (0..1_000_000).step_by(997) {
+        let expected = data.binary_search(&key).is_ok();
+        assert_eq!(search_by_key(&Binary, &data, key, |&n| n, &mut binary).is_ok(), expected);
+        assert_eq!(search_by_key(&Interpolation, &data, key, |&n| n, &mut interpolation).is_ok(), expected);
+        assert_eq!(search_by_key(&Bis, &data, key, |&n| n, &mut bis).is_ok(), expected);
+    }
+    // Evenly spread keys: both interpolating searches need far fewer probes.
+    assert!(interpolation.probes * 3 < binary.probes, "{:?} vs {:?}", interpolation, binary);
+    assert!(bis.probes * 2 < binary.probes, "{:?} vs {:?}", bis, binary);
+    assert_eq!(binary.jumps, 0);
+
+    let records = sample_records();
+    let date = TradeDate::parse("09/01/2015").unwrap();
+    assert_eq!(records[bis_date(&records, date).unwrap()].date, "09/01/2015");
+    assert_eq!(bis_date(&records, TradeDate::parse("01/01/2016").unwrap()), Err(records.len()));
+}
+
+#[test]
+fn test_search_benchmark_workloads() {
+    let records = sample_records();
+    let dates: Vec<TradeDate> = records.iter().filter_map(|d| d.trade_date().ok()).collect();
+    for workload in Workload::ALL {
+        let queries = workload.queries(&records, 200, 7);
+        assert_eq!(queries.len(), 200);
+        assert_eq!(queries, workload.queries(&records, 200, 7), "{} is not seeded", workload);
+        let hits = queries.iter().filter(|date| dates.contains(date)).count();
+        match workload {
+            Workload::FromData | Workload::Skewed => assert_eq!(hits, 200, "{}", workload),
+            Workload::Misses => assert_eq!(hits, 0),
+            Workload::Uniform => assert!(queries.iter().all(|date| (dates[0]..=dates[dates.len() - 1]).contains(date))),
+        }
+        assert_eq!(workload.name().parse::<Workload>(), Ok(workload));
+    }
+    let skewed = Workload::Skewed.queries(&records, 200, 7);
+    assert!(skewed.iter().filter(|&&date| date <= dates[dates.len() / 2]).count() > 150);
+    assert!(Workload::Uniform.queries(&[], 10, 7).is_empty());
+    assert!("zipf".parse::<Workload>().is_err());
+}
+
+#[test]
+fn test_search_benchmark_report() {
+    let records = sample_records();
+    let results = SearchBenchmark::new().queries(50).repeats(2).run(&records, &SEARCHES, &[Workload::FromData, Workload::Misses]);
+    assert_eq!(results.len(), 2 * SEARCHES.len());
+    for result in &results {
+        assert_eq!(result.queries, 50);
+        let expected_hits = if result.workload == Workload::FromData { 50 } else { 0 };
+        assert_eq!(result.hits, expected_hits, "{} on {}", result.search, result.workload);
+        assert!(result.median <= result.p99 && result.mean_probes <= result.max_probes as f64);
+    }
+
+    let mut csv = Vec::new();
+    write_search_report(&results, ReportFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("search,workload,queries,hits,mean_ns,median_ns,p99_ns,mean_probes,max_probes,mean_jumps\n"));
+    assert_eq!(csv.lines().count(), results.len() + 1);
+    assert!(csv.lines().nth(1).unwrap().starts_with("binary,from-data,50,50,"));
+
+    let mut json = Vec::new();
+    write_search_report(&results[..2], ReportFormat::Json, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with("[\n  {\"search\": \"binary\", \"workload\": \"from-data\", \"queries\": 50,"));
+    assert!(json.trim_end().ends_with("}\n]"));
+    assert_eq!(json.matches("},").count(), 1);
+
+    let mut table = Vec::new();
+    write_search_report(&results, ReportFormat::Table, &mut table).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap().lines().count(), results.len() + 1);
+    assert_eq!("json".parse::<ReportFormat>(), Ok(ReportFormat::Json));
+}
+
+#[test]
+fn test_sort_benchmark_inputs() {
+    let records = sample_records();
+    for input in SortInput::ALL {
+        let data = input.records(&records, 300, 3);
+        assert_eq!(data.len(), 300);
+        assert!(data.iter().enumerate().all(|(i, d)| d.cumulative == i as u64));
+        let values: Vec<u64> = data.iter().map(|d| d.value).collect();
+        match input {
+            SortInput::Sorted => assert!(values.is_sorted()),
+            SortInput::Reversed => assert!(values.windows(2).all(|w| w[0] > w[1])),
+            SortInput::Duplicates => assert!(values.iter().all(|v| v % 1_000 == 0 && v / 1_000 < 16)),
+            SortInput::Real => assert!(data.iter().all(|d| records.iter().any(|r| r.date == d.date && r.value == d.value))),
+            SortInput::Random => assert!(!values.is_sorted()),
+        }
+        assert_eq!(input.name().parse::<SortInput>(), Ok(input));
+    }
+    assert!(SortInput::Real.records(&[], 10, 3).is_empty());
+
+    for algorithm in SortAlgorithm::ALL {
+        let mut data = SortInput::Random.records(&[], 1_000, 3);
+        algorithm.sort(&mut data);
+        assert!(data.is_sorted_by_key(|d| d.value), "{}", algorithm);
+    }
+}
+
+#[test]
+fn test_sort_benchmark_measures_each_thread_count_once() {
+    let results = SortBenchmark::new()
+        .sizes(vec![50])
+        .threads(vec![2, 1, 2, 1])
+        .repeats(1)
+        .run(&[], &[SortAlgorithm::Merge], &[SortInput::Random])
+        .unwrap();
+    let threads: Vec<usize> = results.iter().map(|result| result.threads).collect();
+    assert_eq!(threads, [1, 2]);
+}
+
+#[test]
+fn test_sort_benchmark_report() {
+    let results = SortBenchmark::new()
+        .sizes(vec![200])
+        .threads(vec![1, 2])
+        .repeats(1)
+        .run(&[], &SortAlgorithm::ALL, &[SortInput::Sorted, SortInput::Reversed, SortInput::Real])
+        .unwrap();
+    // The real input is skipped without data.
+    assert_eq!(results.len(), 2 * 2 * SortAlgorithm::ALL.len());
+    for result in &results {
+        assert_eq!(result.size, 200);
+        assert!(result.min <= result.mean);
+        // The test binary keeps the system allocator.
+        assert_eq!(result.peak_memory, None);
+        match result.algorithm {
+            SortAlgorithm::Counting => assert_eq!(result.comparisons, 0),
+            _ => assert!(result.comparisons >= 199, "{:?}", result),
+        }
+    }
+
+    let mut csv = Vec::new();
+    write_sort_report(&results[..1], ReportFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("algorithm,input,size,threads,mean_ms,min_ms,comparisons,peak_bytes\ncounting,sorted,200,1,"));
+    assert!(csv.trim_end().ends_with(",0,"));
+
+    let mut json = Vec::new();
+    write_sort_report(&results[..1], ReportFormat::Json, &mut json).unwrap();
+    assert!(String::from_utf8(json).unwrap().contains("\"comparisons\": 0, \"peak_bytes\": null}"));
+    assert!("bogo".parse::<SortAlgorithm>().is_err());
+}
+
+// Keys in a scrambled order, with repeats.
+fn avl_keys() -> Vec<u64> {
+    (0..2000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % 700).collect()
+}
+
+#[test]
+fn test_avl_tree_matches_btree_map() {
+    let mut tree = AvlTree::new();
+    let mut map = BTreeMap::new();
+    for (i, key) in avl_keys().into_iter().enumerate() {
+        assert_eq!(tree.insert(key, i), map.insert(key, i));
+    }
+    assert_eq!(tree.len(), map.len());
+    assert!(tree.iter().eq(map.iter()));
+    assert_eq!(tree.first_key_value(), map.first_key_value());
+    assert_eq!(tree.last_key_value(), map.last_key_value());
+    // An AVL tree of n nodes is at most about 1.44 * log2(n) high.
+    assert!(tree.height() as f64 <= 1.45 * (tree.len() as f64 + 2.0).log2(), "height {}", tree.height());
+
+    for key in (0..800).step_by(3) {
+        assert_eq!(tree.remove(&key), map.remove(&key));
+        assert_eq!(tree.contains_key(&key), map.contains_key(&key));
+    }
+    *tree.get_mut(&1).unwrap() += 10;
+    *map.get_mut(&1).unwrap() += 10;
+    assert_eq!(tree.len(), map.len());
+    assert!(tree.iter().eq(map.iter()));
+    assert_eq!(tree.get(&1), map.get(&1));
+    assert_eq!(tree.get(&3), None);
+    assert!(tree.height() as f64 <= 1.45 * (tree.len() as f64 + 2.0).log2(), "height {}", tree.height());
+
+    while let Some((&key, _)) = tree.first_key_value() {
+        tree.remove(&key);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 0);
+}
+
+#[test]
+fn test_avl_tree_entry() {
+    let mut tree: AvlTree<String, Vec<u64>> = AvlTree::new();
+    for (i, word) in ["b", "a", "b", "c", "b"].into_iter().enumerate() {
+        tree.entry(word.to_string()).or_default().push(i as u64);
+    }
+    assert_eq!(tree.get("b"), Some(&vec![0, 2, 4]));
+    assert_eq!(tree.len(), 3);
+
+    tree.entry("a".to_string()).and_modify(|v| v.clear()).or_insert_with(|| vec![9]);
+    tree.entry("d".to_string()).and_modify(|v| v.clear()).or_insert_with(|| vec![9]);
+    assert_eq!(tree.get("a"), Some(&vec![]));
+    assert_eq!(tree.get("d"), Some(&vec![9]));
+
+    match tree.entry("c".to_string()) {
+        Entry::Occupied(entry) => assert_eq!(entry.remove(), vec![3]),
+        Entry::Vacant(_) => panic!("c is in the tree"),
+    }
+    assert!(matches!(tree.entry("c".to_string()), Entry::Vacant(_)));
+    assert_eq!(tree.len(), 3);
+}
+
+#[test]
+fn test_avl_tree_from_iterator_and_extend() {
+    let mut tree: AvlTree<u64, char> = [(3, 'c'), (1, 'a'), (2, 'x')].into_iter().collect();
+    tree.extend([(2, 'b'), (4, 'd')]);
+    assert_eq!(tree.iter().map(|(_, &v)| v).collect::<String>(), "abcd");
+    assert_eq!(format!("{:?}", tree), "{1: 'a', 2: 'b', 3: 'c', 4: 'd'}");
+    assert_eq!(tree.iter().len(), 4);
+}
+
+#[test]
+fn test_avl_tree_iterators() {
+    let map: BTreeMap<u64, usize> = avl_keys().into_iter().enumerate().map(|(i, key)| (key, i)).collect();
+    let mut tree: AvlTree<u64, usize> = map.iter().map(|(&key, &value)| (key, value)).collect();
+
+    assert!(tree.iter().rev().eq(map.iter().rev()));
+    // Both ends meet in the middle without repeating an entry.
+    let mut iter = tree.iter();
+    let mut seen = Vec::new();
+    while let Some((&key, _)) = if seen.len() % 3 == 0 { iter.next_back() } else { iter.next() } {
+        seen.push(key);
+        assert_eq!(iter.len(), map.len() - seen.len());
+    }
+    seen.sort_unstable();
+    assert!(seen.iter().eq(map.keys()));
+
+    for (key, value) in &mut tree {
+        *value = *key as usize * 2;
+    }
+    assert!(tree.iter_mut().rev().all(|(key, value)| *value == *key as usize * 2));
+    let owned: Vec<(u64, usize)> = tree.into_iter().collect();
+    assert!(owned.iter().map(|(key, _)| key).eq(map.keys()));
+}
+
+#[test]
+fn test_avl_tree_range() {
+    let map: BTreeMap<u64, ()> = avl_keys().into_iter().map(|key| (key, ())).collect();
+    let tree: AvlTree<u64, ()> = map.iter().map(|(&key, _)| (key, ())).collect();
+
+    let bounds = |b: u64| [Bound::Included(b), Bound::Excluded(b), Bound::Unbounded];
+    for (from, to) in [(0, 699), (5, 6), (100, 350), (351, 352), (600, 800), (800, 900)] {
+        for start in bounds(from) {
+            for end in bounds(to) {
+                assert!(tree.range((start, end)).eq(map.range((start, end))), "{:?}..{:?}", start, end);
+                assert!(tree.range((start, end)).rev().eq(map.range((start, end)).rev()), "{:?}..{:?}", start, end);
+                assert_eq!(tree.range((start, end)).len(), map.range((start, end)).count());
+            }
+        }
+    }
+    assert_eq!(tree.range(10..10).count(), 0);
+    assert!(tree.range(..).eq(map.iter()));
+}
+
+#[test]
+fn test_avl_tree_neighbours() {
+    let tree: AvlTree<u64, char> = [(10, 'a'), (20, 'b'), (30, 'c')].into_iter().collect();
+    assert_eq!(tree.floor(&20), Some((&20, &'b')));
+    assert_eq!(tree.floor(&25), Some((&20, &'b')));
+    assert_eq!(tree.floor(&5), None);
+    assert_eq!(tree.ceiling(&20), Some((&20, &'b')));
+    assert_eq!(tree.ceiling(&25), Some((&30, &'c')));
+    assert_eq!(tree.ceiling(&35), None);
+    assert_eq!(tree.predecessor(&20), Some((&10, &'a')));
+    assert_eq!(tree.predecessor(&10), None);
+    assert_eq!(tree.successor(&20), Some((&30, &'c')));
+    assert_eq!(tree.successor(&0), Some((&10, &'a')));
+    assert_eq!(tree.successor(&30), None);
+}
+
+#[test]
+fn test_avl_tree_order_statistics() {
+    let mut map: BTreeMap<u64, ()> = avl_keys().into_iter().map(|key| (key, ())).collect();
+    let mut tree: AvlTree<u64, ()> = map.iter().map(|(&key, _)| (key, ())).collect();
+    // Removals rotate the tree too; the counts must survive them.
+    for key in (0..700).step_by(7) {
+        tree.remove(&key);
+        map.remove(&key);
+    }
+
+    let keys: Vec<u64> = map.keys().copied().collect();
+    for (k, key) in keys.iter().enumerate() {
+        assert_eq!(tree.select(k), Some((key, &())));
+        assert_eq!(tree.rank(key), k);
+    }
+    assert_eq!(tree.select(keys.len()), None);
+    assert_eq!(tree.rank(&0), 0);
+    assert_eq!(tree.rank(&7), keys.partition_point(|&key| key < 7));
+    assert_eq!(tree.rank(&10_000), keys.len());
+
+    for (a, b) in [(0, 699), (3, 3), (7, 14), (100, 350), (650, 900)] {
+        assert_eq!(tree.count_in_range(a..=b), map.range(a..=b).count(), "{}..={}", a, b);
+        assert_eq!(tree.count_in_range(a..b), map.range(a..b).count(), "{}..{}", a, b);
+        assert_eq!(tree.count_in_range((Bound::Excluded(a), Bound::Included(b))), map.range((Bound::Excluded(a), Bound::Included(b))).count());
+    }
+    assert_eq!(tree.count_in_range(..), tree.len());
+    assert_eq!(tree.count_in_range((Bound::Included(500), Bound::Included(100))), 0);
+}
+
+// Sum and maximum of the values, to check the stored subtree aggregates.
+#[derive(Clone, Debug, PartialEq)]
+struct SumMax(u64, u64);
+
+impl Aggregate<u64> for SumMax {
+    fn from_value(value: &u64) -> SumMax {
+        SumMax(*value, *value)
+    }
+
+    fn combine(&self, other: &SumMax) -> SumMax {
+        SumMax(self.0 + other.0, self.1.max(other.1))
+    }
+}
+
+#[test]
+fn test_avl_tree_aggregates() {
+    let mut map = BTreeMap::new();
+    let mut tree: AvlTree<u64, u64, SumMax> = AvlTree::default();
+    for (i, key) in avl_keys().into_iter().enumerate() {
+        let value = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) % 1000;
+        map.insert(key, value);
+        tree.insert(key, value);
+    }
+    // Removals rotate the tree, replacing and modifying change values in place.
+    for key in (0..700).step_by(5) {
+        assert_eq!(tree.remove(&key), map.remove(&key));
+    }
+    for key in (1..700).step_by(11) {
+        assert_eq!(tree.insert(key, 5000 + key), map.insert(key, 5000 + key));
+        let doubled = tree.modify(&(key + 1), |value| {
+            *value *= 2;
+            *value
+        });
+        if let Some(value) = map.get_mut(&(key + 1)) {
+            *value *= 2;
+        }
+        assert_eq!(doubled, map.get(&(key + 1)).copied());
+    }
+
+    let expected = |range: std::ops::RangeInclusive<u64>| {
+        let values: Vec<u64> = map.range(range).map(|(_, &value)| value).collect();
+        values.iter().max().map(|&max| SumMax(values.iter().sum(), max))
+    };
+    for (from, to) in [(0, 699), (3, 3), (5, 5), (10, 90), (200, 650), (690, 900)] {
+        assert_eq!(tree.aggregate(from..=to), expected(from..=to), "{}..={}", from, to);
+    }
+    assert_eq!(tree.aggregate(..), expected(0..=u64::MAX));
+    assert_eq!(tree.aggregate(800..), None);
+    assert_eq!(tree.modify(&5, |value| *value), None);
+}
+
+#[test]
+fn test_date_tree_summary_follows_changes() {
+    let date = TradeDate::parse("03/01/2015").unwrap();
+    let record = |value| Data { date: "03/01/2015".to_string(), value, ..Data::new() };
+    let mut tree = DateTree::new();
+    for value in [5, 2, 9, 4, 7] {
+        tree.insert(&record(value));
+    }
+    tree.insert(&Data { date: "04/01/2015".to_string(), value: 100, ..Data::new() });
+    let check = |tree: &DateTree| assert_eq!(tree.summary(date..=date), ValueSummary::of_all(tree.search(date).unwrap()));
+    check(&tree);
+
+    // Changes to values in the middle and to the extremes alike.
+    assert!(tree.edit(date, 3, 6));
+    check(&tree);
+    assert!(tree.edit(date, 2, 1));
+    check(&tree);
+    assert!(!tree.edit(date, 5, 1));
+    assert_eq!(tree.delete(date, 4).unwrap().value, 7);
+    check(&tree);
+    assert_eq!(tree.delete(date, 2).unwrap().value, 1);
+    check(&tree);
+    assert!(tree.delete(date, 3).is_none());
+    assert_eq!(tree.summary(date..=date), ValueSummary { count: 3, sum: 13, min: 2, max: 6 });
+    assert_eq!(tree.summary(date..).sum, 113);
+}
+
+#[test]
+fn test_date_tree_select() {
+    let date = TradeDate::parse("03/01/2015").unwrap();
+    let record = |country: &str| Data { date: "03/01/2015".to_string(), country: country.to_string(), ..Data::new() };
+    let mut tree = DateTree::new();
+    for country in ["China", "Japan", "China"] {
+        tree.insert(&record(country));
+    }
+
+    let japan = RecordFilter { country: Some(" japan ".to_string()), ..RecordFilter::default() };
+    let china = RecordFilter { country: Some("China".to_string()), ..RecordFilter::default() };
+    assert_eq!(tree.select(date, &japan), Ok(1));
+    assert_eq!(tree.select(date, &china), Err(SelectError::Ambiguous(2)));
+    assert_eq!(tree.select(TradeDate::from_days(date.days() + 1), &japan), Err(SelectError::DateNotFound));
+    assert_eq!(tree.delete(date, 1).unwrap().country, "Japan");
+    assert_eq!(tree.select(date, &japan), Err(SelectError::NoMatch));
+}